@@ -84,6 +84,9 @@ for obj in bpy.context.scene.objects:
         # Select only the current object for export
         bpy.ops.object.select_all(action='DESELECT')
         obj.select_set(True)
+        # Children carry Item* slots and COL_* collision shapes
+        for child in obj.children_recursive:
+            child.select_set(True)
         bpy.context.view_layer.objects.active = obj
         
         # Define output file path
@@ -1,33 +1,29 @@
 // Retro Arcade Shader (Pixelation + Yellow Tint)
-@group(0) @binding(0) var textureSampler: sampler;
-@group(0) @binding(1) var textureData: texture_2d<f32>;
+// Post processing pass over the game camera, see `RetroArcadePlugin`
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
 
-struct VertexOutput {
-    @builtin(position) position: vec4<f32>,
-    @location(0) uv: vec2<f32>
-};
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
 
-@vertex
-fn vertex_main(@location(0) position: vec2<f32>, @location(1) uv: vec2<f32>) -> VertexOutput {
-    var out: VertexOutput;
-    out.position = vec4<f32>(position, 0.0, 1.0);
-    out.uv = uv;
-    return out;
+struct RetroArcade {
+    // Physical size of the camera's image
+    resolution: vec2<f32>,
+    pixel_size: f32,
+    tint: vec3<f32>,
 }
+@group(0) @binding(2) var<uniform> settings: RetroArcade;
 
 @fragment
-fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // Pixelation parameters
-    let pixelSize: vec2<f32> = vec2<f32>(5.0, 5.0); // Adjust for more or less pixelation
-    let screenRes: vec2<f32> = vec2<f32>(800.0, 600.0); // Adjust based on actual screen size
-    let uv_pixelated: vec2<f32> = floor(in.uv * screenRes / pixelSize) * pixelSize / screenRes;
-    
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // Snap to the middle of each block so the whole block gets one color
+    let block: vec2<f32> = vec2<f32>(settings.pixel_size) / settings.resolution;
+    let uv_pixelated: vec2<f32> = (floor(in.uv / block) + 0.5) * block;
+
     // Sample the texture with pixelated UVs
-    let color: vec4<f32> = textureSample(textureData, textureSampler, uv_pixelated);
-    
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, uv_pixelated);
+
     // Apply a slight yellow tint
-    let yellowTint: vec3<f32> = vec3<f32>(1.2, 1.1, 0.8); // Warmer tones
-    let finalColor: vec3<f32> = color.rgb * yellowTint;
-    
+    let finalColor: vec3<f32> = color.rgb * settings.tint;
+
     return vec4<f32>(finalColor, color.a);
 }
@@ -3,22 +3,70 @@ use crate::state::InGameState;
 use bevy::core_pipeline::bloom::{Bloom, BloomSettings};
 use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::{
-    default, in_state, App, Asset, Assets, ButtonInput, Camera, Camera2d, Camera3d,
-    ClearColorConfig, Commands, Component, EventReader, IntoSystemConfigs, Material,
-    MaterialPlugin, MeshMaterial3d, MouseButton, Plugin, Quat, Query, RayCastPickable, Res, ResMut,
-    Resource, Startup, Time, Transform, TypePath, Update, Vec3, With, Without,
+    default, in_state, resource_equals, App, Asset, Assets, ButtonInput, Camera, Camera2d,
+    Camera3d, ClearColorConfig, Commands, Component, EulerRot, EventReader, IntoSystemConfigs,
+    KeyCode, MeshMaterial3d, MouseButton, Plugin, Quat, Query, RayCastPickable, Reflect,
+    ReflectResource, Res, ResMut, Resource, Startup, Time, Timer, TimerMode, Transform, TypePath,
+    Update, Vec3, With, Without,
 };
-use bevy::render::render_resource::{AsBindGroup, ShaderRef};
-use bevy::sprite::Material2d;
+use bevy_inspector_egui::prelude::ReflectInspectorOptions;
+use bevy_inspector_egui::InspectorOptions;
 use blenvy::MaterialMeshBundle;
 
+const CAMERA_MODE_BLEND_SECS: f32 = 0.6;
+
 #[derive(Component)]
 pub struct UICamera;
 
 #[derive(Component)]
 pub struct GameCamera;
 
+#[derive(Resource, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum CameraMode {
+    #[default]
+    Chase,
+    TopDown,
+    Fly,
+}
+impl CameraMode {
+    fn next(self) -> Self {
+        match self {
+            CameraMode::Chase => CameraMode::TopDown,
+            CameraMode::TopDown => CameraMode::Chase,
+            CameraMode::Fly => CameraMode::Chase,
+        }
+    }
+}
+
+/// Transform driven by the active camera mode, before any blending is applied.
+#[derive(Resource, Default)]
+pub struct CameraRig(pub Transform);
+
 #[derive(Resource)]
+pub struct CameraModeBlend {
+    from: Transform,
+    timer: Timer,
+}
+impl Default for CameraModeBlend {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(CAMERA_MODE_BLEND_SECS, TimerMode::Once);
+        timer.tick(timer.duration());
+        CameraModeBlend {
+            from: Transform::default(),
+            timer,
+        }
+    }
+}
+impl CameraModeBlend {
+    pub fn start(&mut self, from: Transform) {
+        self.from = from;
+        self.timer.reset();
+    }
+}
+
+#[derive(Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
 pub struct PlayerCameraOffset {
     yaw: f32,
     pitch: f32,
@@ -34,20 +82,82 @@ impl Default for PlayerCameraOffset {
     }
 }
 
+#[derive(Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct TopDownCameraSettings {
+    height: f32,
+    min_height: f32,
+    max_height: f32,
+    tilt: f32,
+    follow_speed: f32,
+}
+impl Default for TopDownCameraSettings {
+    fn default() -> Self {
+        TopDownCameraSettings {
+            height: 25.0,
+            min_height: 10.0,
+            max_height: 60.0,
+            tilt: 0.2,
+            follow_speed: 3.0,
+        }
+    }
+}
+
+#[derive(Resource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
+pub struct FlyCameraSettings {
+    speed: f32,
+    fast_multiplier: f32,
+    sensitivity: f32,
+    yaw: f32,
+    pitch: f32,
+}
+impl Default for FlyCameraSettings {
+    fn default() -> Self {
+        FlyCameraSettings {
+            speed: 8.0,
+            fast_multiplier: 4.0,
+            sensitivity: 0.005,
+            yaw: 0.0,
+            pitch: 0.0,
+        }
+    }
+}
+
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_camera);
         app.add_systems(
             Update,
-            (update_camera_offset, follow_player_with_offsets)
+            (
+                switch_camera_mode,
+                (update_camera_offset, follow_player_with_offsets)
+                    .chain()
+                    .run_if(resource_equals(CameraMode::Chase)),
+                (update_top_down_height, follow_player_top_down)
+                    .chain()
+                    .run_if(resource_equals(CameraMode::TopDown)),
+                fly_camera.run_if(resource_equals(CameraMode::Fly)),
+                apply_camera_rig,
+            )
+                .chain()
                 .run_if(in_state(InGameState::Playing)),
         );
+        app.init_resource::<CameraMode>();
+        app.init_resource::<CameraModeBlend>();
         app.insert_resource(PlayerCameraOffset::default());
+        app.init_resource::<TopDownCameraSettings>();
+        app.init_resource::<FlyCameraSettings>();
+        app.register_type::<CameraMode>();
+        app.register_type::<PlayerCameraOffset>();
+        app.register_type::<TopDownCameraSettings>();
+        app.register_type::<FlyCameraSettings>();
     }
 }
 
 fn setup_camera(mut commands: Commands) {
+    let camera_t = Transform::from_xyz(5.0, 15.0, 0.0).looking_at(Vec3::ZERO, Vec3::Y);
     commands.spawn((
         Camera3d::default(),
         Camera {
@@ -55,11 +165,12 @@ fn setup_camera(mut commands: Commands) {
             order: 1,
             ..default()
         },
-        Transform::from_xyz(5.0, 15.0, 0.0).looking_at(Vec3::ZERO, Vec3::Y),
+        camera_t,
         GameCamera,
         Bloom::NATURAL,
         RayCastPickable,
     ));
+    commands.insert_resource(CameraRig(camera_t));
     commands.spawn((
         Camera2d::default(),
         Camera {
@@ -72,17 +183,49 @@ fn setup_camera(mut commands: Commands) {
     ));
 }
 
+fn switch_camera_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut camera_mode: ResMut<CameraMode>,
+    mut camera_rig: ResMut<CameraRig>,
+    mut blend: ResMut<CameraModeBlend>,
+    mut fly_settings: ResMut<FlyCameraSettings>,
+    camera_q: Query<&Transform, With<GameCamera>>,
+) {
+    let next_mode = if keys.just_pressed(KeyCode::KeyC) {
+        Some(camera_mode.next())
+    } else if cfg!(debug_assertions) && keys.just_pressed(KeyCode::KeyV) {
+        // Free fly camera is a level design tool, only available in debug builds
+        if *camera_mode == CameraMode::Fly {
+            Some(CameraMode::Chase)
+        } else {
+            Some(CameraMode::Fly)
+        }
+    } else {
+        None
+    };
+    if let Some(mode) = next_mode {
+        if let Ok(camera_t) = camera_q.get_single() {
+            // Blend from wherever the camera currently is, and let the new mode start there
+            blend.start(*camera_t);
+            camera_rig.0 = *camera_t;
+            if mode == CameraMode::Fly {
+                let (yaw, pitch, _) = camera_t.rotation.to_euler(EulerRot::YXZ);
+                fly_settings.yaw = yaw;
+                fly_settings.pitch = pitch;
+            }
+        }
+        *camera_mode = mode;
+    }
+}
+
 fn follow_player_with_offsets(
     player_q: Query<&Transform, (Without<GameCamera>, With<Player>)>,
-    mut camera_q: Query<&mut Transform, (Without<Player>, With<GameCamera>)>,
+    mut camera_rig: ResMut<CameraRig>,
     camera_offset: Res<PlayerCameraOffset>,
     time: Res<Time>,
 ) {
     if let Ok(player_t) = player_q.get_single() {
-        let mut camera_t = camera_q.single_mut();
-
-        // Compute player's forward direction
-        let player_forward = player_t.rotation * Vec3::Z; // Player's local +Z direction
+        let camera_t = &mut camera_rig.0;
 
         // Compute base offset in player's local space (behind and above the player)
         let base_offset = Vec3::new(0.0, 3.0, camera_offset.distance);
@@ -128,3 +271,108 @@ fn update_camera_offset(
         camera_offset.distance = (camera_offset.distance - event.y * 0.5).clamp(2.0, 10.0);
     }
 }
+
+fn update_top_down_height(
+    mut top_down_settings: ResMut<TopDownCameraSettings>,
+    mut scroll_events: EventReader<MouseWheel>,
+) {
+    for event in scroll_events.read() {
+        top_down_settings.height = (top_down_settings.height - event.y * 2.0)
+            .clamp(top_down_settings.min_height, top_down_settings.max_height);
+    }
+}
+
+fn follow_player_top_down(
+    player_q: Query<&Transform, (Without<GameCamera>, With<Player>)>,
+    mut camera_rig: ResMut<CameraRig>,
+    top_down_settings: Res<TopDownCameraSettings>,
+    time: Res<Time>,
+) {
+    if let Ok(player_t) = player_q.get_single() {
+        let camera_t = &mut camera_rig.0;
+
+        // Stay above the player, tilted slightly so the store keeps a sense of depth
+        let target_position = player_t.translation
+            + Vec3::new(
+                0.0,
+                top_down_settings.height,
+                top_down_settings.height * top_down_settings.tilt.tan(),
+            );
+        camera_t.translation = camera_t.translation.lerp(
+            target_position,
+            1.0 - (-time.delta_secs() * top_down_settings.follow_speed).exp(),
+        );
+
+        // Keep the store's -Z pointing up the screen, regardless of the cart's rotation
+        camera_t.look_at(player_t.translation, Vec3::NEG_Z);
+    }
+}
+
+fn fly_camera(
+    mut camera_rig: ResMut<CameraRig>,
+    mut fly_settings: ResMut<FlyCameraSettings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    time: Res<Time>,
+) {
+    if mouse_input.pressed(MouseButton::Right) {
+        for event in mouse_motion_events.read() {
+            fly_settings.yaw -= event.delta.x * fly_settings.sensitivity;
+            fly_settings.pitch = (fly_settings.pitch - event.delta.y * fly_settings.sensitivity)
+                .clamp(
+                    -std::f32::consts::FRAC_PI_2 + 0.01,
+                    std::f32::consts::FRAC_PI_2 - 0.01,
+                );
+        }
+    }
+    let camera_t = &mut camera_rig.0;
+    camera_t.rotation = Quat::from_euler(EulerRot::YXZ, fly_settings.yaw, fly_settings.pitch, 0.0);
+
+    // Arrow keys so WASD can keep driving the cart underneath
+    let mut direction = Vec3::ZERO;
+    if keys.pressed(KeyCode::ArrowUp) {
+        direction += *camera_t.forward();
+    }
+    if keys.pressed(KeyCode::ArrowDown) {
+        direction -= *camera_t.forward();
+    }
+    if keys.pressed(KeyCode::ArrowLeft) {
+        direction -= *camera_t.right();
+    }
+    if keys.pressed(KeyCode::ArrowRight) {
+        direction += *camera_t.right();
+    }
+    if keys.pressed(KeyCode::PageUp) {
+        direction += Vec3::Y;
+    }
+    if keys.pressed(KeyCode::PageDown) {
+        direction -= Vec3::Y;
+    }
+    let speed = fly_settings.speed
+        * if keys.pressed(KeyCode::ShiftLeft) {
+            fly_settings.fast_multiplier
+        } else {
+            1.0
+        };
+    camera_t.translation += direction.normalize_or_zero() * speed * time.delta_secs();
+}
+
+fn apply_camera_rig(
+    camera_rig: Res<CameraRig>,
+    mut blend: ResMut<CameraModeBlend>,
+    mut camera_q: Query<&mut Transform, With<GameCamera>>,
+    time: Res<Time>,
+) {
+    if let Ok(mut camera_t) = camera_q.get_single_mut() {
+        if blend.timer.tick(time.delta()).finished() {
+            *camera_t = camera_rig.0;
+        } else {
+            // Smoothstep between the camera before the switch and the new mode's rig
+            let t = blend.timer.fraction();
+            let eased = t * t * (3.0 - 2.0 * t);
+            camera_t.translation = blend.from.translation.lerp(camera_rig.0.translation, eased);
+            camera_t.rotation = blend.from.rotation.slerp(camera_rig.0.rotation, eased);
+        }
+    }
+}
@@ -1,12 +1,14 @@
-use crate::game::effects::particles::spawn_particle;
-use crate::game::movement::StepParticleAssets;
-use crate::game::player::Player;
+use crate::game::effects::particles::{ParticleEmitters, ParticlePreset};
+use crate::game::rng::GameRng;
 use crate::hierarchy::get_root_parent_entity;
 use crate::state::InGameState;
 use bevy::animation::{AnimationPlayer, AnimationTarget};
 use bevy::app::{App, Plugin, Update};
-use bevy::asset::Handle;
+use bevy::asset::io::Reader;
+use bevy::asset::{Asset, AssetApp, AssetId, AssetLoader, Handle, LoadContext};
 use bevy::color::palettes::basic::WHITE;
+use bevy::core::Name;
+use bevy::ecs::system::SystemParam;
 use bevy::hierarchy::Children;
 use bevy::log::info;
 use bevy::math::Vec3;
@@ -14,134 +16,511 @@ use bevy::pbr::StandardMaterial;
 use bevy::prelude::{
     debug, in_state, warn, Added, AnimationClip, AnimationGraph, AnimationGraphHandle,
     AnimationNodeIndex, AnimationNodeType, AnimationTransitions, Assets, Commands, Component,
-    Entity, Event, FromWorld, HierarchyQueryExt, IntoSystemConfigs, Mesh, Parent, Query, Reflect,
-    Res, ResMut, Resource, Sphere, Transform, Trigger, With, World,
+    Entity, Event, FromWorld, HierarchyQueryExt, IntoSystemConfigs, Local, Mesh, Mut, Parent,
+    Query, Reflect, Res, ResMut, Resource, Sphere, Transform, Trigger, TypePath, Vec3Swizzles,
+    With, Without, World,
 };
 use bevy::scene::SceneInstanceReady;
-use rand::{rng, Rng};
+use bevy::utils::{HashMap, HashSet};
+use bevy_rapier3d::prelude::Velocity;
+use rand::Rng;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
 use std::ops::Div;
 use std::time::Duration;
 
 pub struct AnimationPlugin;
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
+        app.init_asset::<AnimationEventTable>();
+        app.register_asset_loader(AnimationEventTableLoader);
         app.add_systems(
             Update,
-            (link_animations).run_if(in_state(InGameState::Playing)),
+            (
+                link_animations,
+                apply_animation_events,
+                update_animation_state_machines,
+            )
+                .chain()
+                .run_if(in_state(InGameState::Playing)),
         );
+        app.add_observer(forward_animation_marker);
         app.add_observer(observe_on_step);
+        app.add_observer(play_one_shot_animation);
     }
 }
 
+/// The animation players in a root entity's scene, keyed by the name of their glTF node.
+#[derive(Component, Default)]
+pub struct AnimationPlayersForRootEntity {
+    by_name: HashMap<String, Entity>,
+    primary: Option<Entity>,
+}
+impl AnimationPlayersForRootEntity {
+    fn insert(&mut self, name: String, entity: Entity) {
+        if self.by_name.insert(name.clone(), entity).is_some() {
+            warn!("Animation player name {} is used more than once", name);
+        }
+        self.primary.get_or_insert(entity);
+    }
+
+    /// The first player found, for models with only one
+    pub fn primary(&self) -> Option<Entity> {
+        self.primary
+    }
+
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.by_name.get(name).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Entity)> {
+        self.by_name
+            .iter()
+            .map(|(name, entity)| (name.as_str(), *entity))
+    }
+}
+
+/// Looks up the animation players under a root entity.
+#[derive(SystemParam)]
+pub struct RootAnimationPlayers<'w, 's> {
+    link_q: Query<'w, 's, &'static AnimationPlayersForRootEntity>,
+    player_q: Query<
+        'w,
+        's,
+        (
+            &'static mut AnimationPlayer,
+            &'static mut AnimationTransitions,
+        ),
+    >,
+}
+impl RootAnimationPlayers<'_, '_> {
+    /// The player named `name` under `root`, or its primary player if `name` is `None`.
+    pub fn get_mut(
+        &mut self,
+        root: Entity,
+        name: Option<&str>,
+    ) -> Option<(Mut<AnimationPlayer>, Mut<AnimationTransitions>)> {
+        let link = self.link_q.get(root).ok()?;
+        let player = match name {
+            Some(name) => link.get(name),
+            None => link.primary(),
+        }?;
+        self.player_q.get_mut(player).ok()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Push,
+    Run,
+    Stomp,
+    HookThrow,
+    Bump,
+}
+impl AnimationState {
+    /// Plays once over the top of movement, then hands back to it
+    pub fn is_one_shot(&self) -> bool {
+        matches!(
+            self,
+            AnimationState::Stomp | AnimationState::HookThrow | AnimationState::Bump
+        )
+    }
+}
+
+/// Picks the clip an animated root entity plays from its [`Velocity`] and one-shot requests,
+/// blending between them with [`AnimationTransitions`]. States without a clip fall back to
+/// the nearest one that has one, or are skipped.
+#[derive(Component)]
+pub struct AnimationStateMachine {
+    graph: Handle<AnimationGraph>,
+    clips: HashMap<AnimationState, AnimationNodeIndex>,
+    /// Name of the animation player to drive, if the model has more than one
+    player: Option<String>,
+    current: Option<AnimationState>,
+    current_index: Option<AnimationNodeIndex>,
+    one_shot: Option<AnimationState>,
+    last_speed: f32,
+    /// Ground speed that movement clips play at normal speed
+    pub reference_speed: f32,
+    /// Ground speed above which the entity is moving rather than idle
+    pub move_speed: f32,
+    /// Ground speed above which the entity is running
+    pub run_speed: f32,
+    /// Ground speed lost in one frame that counts as bumping into something
+    pub bump_speed_loss: f32,
+    pub blend: Duration,
+}
+impl AnimationStateMachine {
+    pub fn new(graph: Handle<AnimationGraph>) -> Self {
+        AnimationStateMachine {
+            graph,
+            clips: HashMap::new(),
+            player: None,
+            current: None,
+            current_index: None,
+            one_shot: None,
+            last_speed: 0.0,
+            reference_speed: 4.0,
+            move_speed: 1.0,
+            run_speed: 8.0,
+            bump_speed_loss: 4.0,
+            blend: Duration::from_millis(250),
+        }
+    }
+
+    pub fn with_clip(mut self, state: AnimationState, index: AnimationNodeIndex) -> Self {
+        self.clips.insert(state, index);
+        self
+    }
+
+    pub fn with_player(mut self, name: impl Into<String>) -> Self {
+        self.player = Some(name.into());
+        self
+    }
+
+    pub fn state(&self) -> Option<AnimationState> {
+        self.current
+    }
+
+    pub fn clip(&self, state: AnimationState) -> Option<AnimationNodeIndex> {
+        let fallback = match state {
+            AnimationState::Run => Some(AnimationState::Push),
+            AnimationState::Push => Some(AnimationState::Run),
+            _ => None,
+        };
+        self.clips
+            .get(&state)
+            .or_else(|| fallback.and_then(|fallback| self.clips.get(&fallback)))
+            .copied()
+    }
+}
+
+/// Ask the [`AnimationStateMachine`] on the target entity to play a one-shot state.
+#[derive(Event)]
+pub struct PlayOneShotAnimation(pub AnimationState);
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationEventKind {
+    Footstep,
+    StompImpact,
+    HandRelease,
+}
+
+/// Triggered on the root entity when one of its animations passes a marker from its
+/// [`AnimationEventSource`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AnimationEvent(pub AnimationEventKind);
+
+// Added to clips; fires on the animation player entity, then is forwarded to the root
+#[derive(Event, Clone)]
+struct AnimationMarker(AnimationEventKind);
+
+#[derive(Deserialize, Debug)]
+pub struct AnimationEventMarker {
+    pub time: f32,
+    pub event: AnimationEventKind,
+}
+
+/// Events along a model's clips, loaded from a `.events.ron` sidecar next to the model. Clips
+/// are keyed by their glTF label, e.g. `Animation0`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct AnimationEventTable {
+    pub clips: std::collections::HashMap<String, Vec<AnimationEventMarker>>,
+}
+
+/// Adds the events from a table to the clips of this root entity's animation graph.
 #[derive(Component)]
-pub struct AnimationPlayerEntityForRootEntity(pub Entity);
+pub struct AnimationEventSource(pub Handle<AnimationEventTable>);
 
 #[derive(Component)]
-pub struct AnimationToPlay {
-    pub graph_handle: Handle<AnimationGraph>,
-    pub index: AnimationNodeIndex,
+struct AnimationEventsApplied;
+
+#[derive(Debug)]
+pub enum AnimationEventTableLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
 }
+impl Display for AnimationEventTableLoaderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnimationEventTableLoaderError::Io(e) => {
+                write!(f, "Could not read animation events: {e}")
+            }
+            AnimationEventTableLoaderError::Ron(e) => write!(f, "Invalid animation events: {e}"),
+        }
+    }
+}
+impl std::error::Error for AnimationEventTableLoaderError {}
+
+struct AnimationEventTableLoader;
+impl AssetLoader for AnimationEventTableLoader {
+    type Asset = AnimationEventTable;
+    type Settings = ();
+    type Error = AnimationEventTableLoaderError;
 
-#[derive(Event, Reflect, Clone)]
-pub struct PlayerOnStep;
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<AnimationEventTable, AnimationEventTableLoaderError> {
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(AnimationEventTableLoaderError::Io)?;
+        ron::de::from_bytes(&bytes).map_err(AnimationEventTableLoaderError::Ron)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["events.ron"]
+    }
+}
 
 fn link_animations(
-    animation_player_q: Query<Entity, Added<AnimationPlayer>>,
+    animation_player_q: Query<(Entity, Option<&Name>), Added<AnimationPlayer>>,
     parent_query: Query<&Parent>,
-    existing_link_q: Query<&AnimationPlayerEntityForRootEntity>,
+    mut existing_link_q: Query<&mut AnimationPlayersForRootEntity>,
     mut commands: Commands,
 ) {
+    // Several players can be added under the same root in one frame
+    let mut new_links: HashMap<Entity, AnimationPlayersForRootEntity> = HashMap::new();
     // Find entites where AnimationPlayer is added
-    for entity in animation_player_q.iter() {
+    for (entity, name) in animation_player_q.iter() {
         let root = get_root_parent_entity(entity, &parent_query);
-        if existing_link_q.get(entity).is_ok() {
-            warn!("Additional animation player added for root entity!");
+        let name = name.map_or_else(|| entity.to_string(), |name| name.to_string());
+        debug!("Added link to animation player {}", name);
+        if let Ok(mut link) = existing_link_q.get_mut(root) {
+            link.insert(name, entity);
         } else {
-            commands
-                .entity(root)
-                .insert(AnimationPlayerEntityForRootEntity(entity.clone()));
-            debug!("Added link to animation player entity");
+            new_links.entry(root).or_default().insert(name, entity);
         }
     }
+    for (root, link) in new_links {
+        commands.entity(root).insert(link);
+    }
 }
 
 pub fn setup_animation_graph(
     trigger: Trigger<SceneInstanceReady>,
     mut commands: Commands,
     children: Query<&Children>,
-    animations_to_play: Query<&AnimationToPlay>,
-    mut players: Query<&mut AnimationPlayer>,
-    mut clips: ResMut<Assets<AnimationClip>>,
-    graphs: Res<Assets<AnimationGraph>>,
+    parent_query: Query<&Parent>,
+    state_machine_q: Query<&AnimationStateMachine>,
+    players: Query<Option<&Name>, With<AnimationPlayer>>,
 ) {
-    // Have to find the existing clip in the graph to add events to it
-    fn get_clip<'a>(
-        node: AnimationNodeIndex,
-        graph: &AnimationGraph,
-        clips: &'a mut Assets<AnimationClip>,
-    ) -> &'a mut AnimationClip {
-        let node = graph.get(node).unwrap();
-        let clip = match &node.node_type {
-            AnimationNodeType::Clip(handle) => clips.get_mut(handle),
-            _ => unreachable!(),
-        };
-        clip.unwrap()
-    }
-
-    if let Ok(animation_to_play) = animations_to_play.get(trigger.entity()) {
+    let root = get_root_parent_entity(trigger.entity(), &parent_query);
+    if let Ok(state_machine) = state_machine_q.get(root) {
         for child in children.iter_descendants(trigger.entity()) {
-            if let Ok(mut player) = players.get_mut(child) {
+            let Ok(name) = players.get(child) else {
+                continue;
+            };
+            if state_machine.player.is_none()
+                || state_machine.player.as_deref() == name.map(|name| name.as_str())
+            {
                 info!("found animation player, adding graph handle");
-                let graph = graphs.get(&animation_to_play.graph_handle).unwrap();
-                let animation_clip = get_clip(animation_to_play.index, graph, &mut clips);
-                animation_clip.add_event(0.25, PlayerOnStep);
-                animation_clip.add_event(0.58, PlayerOnStep);
-                info!("clips added");
-                let mut transitions = AnimationTransitions::new();
-                transitions
-                    .play(&mut player, animation_to_play.index, Duration::ZERO)
-                    .repeat();
                 commands
                     .entity(child)
-                    .insert(AnimationGraphHandle(animation_to_play.graph_handle.clone()))
-                    .insert(transitions);
+                    .insert(AnimationGraphHandle(state_machine.graph.clone()))
+                    .insert(AnimationTransitions::new());
             }
         }
     } else {
-        info!("animation player not found");
+        info!("animation state machine not found");
     }
 }
 
-fn observe_on_step(
-    trigger: Trigger<PlayerOnStep>,
+fn apply_animation_events(
     mut commands: Commands,
-    transform_q: Query<&Transform, With<Player>>,
-    particle: Res<StepParticleAssets>,
+    source_q: Query<
+        (
+            Entity,
+            &AnimationEventSource,
+            &AnimationPlayersForRootEntity,
+        ),
+        Without<AnimationEventsApplied>,
+    >,
+    graph_handle_q: Query<&AnimationGraphHandle>,
+    tables: Res<Assets<AnimationEventTable>>,
+    graphs: Res<Assets<AnimationGraph>>,
+    mut clips: ResMut<Assets<AnimationClip>>,
+    // Clips are shared between every instance of a model, so only add events once
+    mut applied_clips: Local<HashSet<AssetId<AnimationClip>>>,
 ) {
-    if let Ok(player_t) = transform_q.get_single() {
-        let mut rng = rng();
-        // Spawn a bunch of particles.
-        for _ in 0..12 {
-            let size = rng.random_range(0.01..0.03);
-            let particle_spawn = player_t.translation
-                + player_t.back().div(Vec3::splat(2.5))
-                + Vec3::new(
-                    rng.random_range(-0.25..0.25),
-                    0.,
-                    rng.random_range(-0.25..0.25),
-                );
-            commands.queue(spawn_particle(
-                particle.mesh.clone(),
-                particle.material.clone(),
-                particle_spawn.reject_from_normalized(Vec3::Y),
-                rng.random_range(0.05..0.15),
-                size,
-                Vec3::new(
-                    player_t.back().x + rng.random_range(-0.5..0.5),
-                    rng.random_range(0.0..4.0),
-                    player_t.back().z + rng.random_range(-0.5..0.5),
-                ),
-            ));
+    for (root, source, animation_players) in &source_q {
+        let Some(table) = tables.get(&source.0) else {
+            continue;
+        };
+        let player_graphs: Option<Vec<&AnimationGraph>> = animation_players
+            .iter()
+            .filter_map(|(_, player)| graph_handle_q.get(player).ok())
+            .map(|graph_handle| graphs.get(graph_handle))
+            .collect();
+        let Some(player_graphs) = player_graphs.filter(|graphs| !graphs.is_empty()) else {
+            continue;
+        };
+        let clip_handles: Vec<&Handle<AnimationClip>> = player_graphs
+            .iter()
+            .flat_map(|graph| {
+                graph
+                    .nodes()
+                    .filter_map(|index| match &graph.get(index)?.node_type {
+                        AnimationNodeType::Clip(handle) => Some(handle),
+                        _ => None,
+                    })
+            })
+            .collect();
+        if clip_handles.iter().any(|handle| !clips.contains(*handle)) {
+            continue;
         }
+
+        for handle in clip_handles {
+            let Some(label) = handle.path().and_then(|path| path.label()) else {
+                continue;
+            };
+            let Some(markers) = table.clips.get(label) else {
+                continue;
+            };
+            if !applied_clips.insert(handle.id()) {
+                continue;
+            }
+            if let Some(clip) = clips.get_mut(handle) {
+                for marker in markers {
+                    clip.add_event(marker.time, AnimationMarker(marker.event));
+                }
+                debug!("added {} animation events to {}", markers.len(), label);
+            }
+        }
+        commands.entity(root).insert(AnimationEventsApplied);
+    }
+}
+
+fn forward_animation_marker(
+    trigger: Trigger<AnimationMarker>,
+    mut commands: Commands,
+    parent_query: Query<&Parent>,
+) {
+    let root = get_root_parent_entity(trigger.entity(), &parent_query);
+    commands.trigger_targets(AnimationEvent(trigger.event().0), root);
+}
+
+fn play_one_shot_animation(
+    trigger: Trigger<PlayOneShotAnimation>,
+    mut state_machine_q: Query<&mut AnimationStateMachine>,
+) {
+    if let Ok(mut state_machine) = state_machine_q.get_mut(trigger.entity()) {
+        state_machine.one_shot = Some(trigger.event().0);
+    }
+}
+
+fn update_animation_state_machines(
+    mut state_machine_q: Query<(Entity, &mut AnimationStateMachine, &Velocity)>,
+    mut players: RootAnimationPlayers,
+) {
+    for (root, mut state_machine, velocity) in &mut state_machine_q {
+        let Some((mut player, mut transitions)) =
+            players.get_mut(root, state_machine.player.as_deref())
+        else {
+            continue;
+        };
+
+        let speed = velocity.linvel.xz().length();
+        if state_machine.last_speed - speed > state_machine.bump_speed_loss {
+            state_machine.one_shot.get_or_insert(AnimationState::Bump);
+        }
+        state_machine.last_speed = speed;
+
+        // Hand back to movement once a one-shot clip has played through
+        if let (Some(current), Some(current_index)) =
+            (state_machine.current, state_machine.current_index)
+        {
+            if current.is_one_shot()
+                && state_machine.one_shot == Some(current)
+                && player
+                    .animation(current_index)
+                    .map_or(true, |animation| animation.is_finished())
+            {
+                state_machine.one_shot = None;
+            }
+        }
+        // One-shots without a clip are dropped rather than interrupting movement
+        if let Some(one_shot) = state_machine.one_shot {
+            if state_machine.clip(one_shot).is_none() {
+                state_machine.one_shot = None;
+            }
+        }
+
+        let target = state_machine
+            .one_shot
+            .unwrap_or(if speed > state_machine.run_speed {
+                AnimationState::Run
+            } else if speed > state_machine.move_speed {
+                AnimationState::Push
+            } else {
+                AnimationState::Idle
+            });
+        if state_machine.current != Some(target) {
+            match state_machine.clip(target) {
+                // Run and push can share a clip, so keep it going rather than restarting it
+                Some(index)
+                    if state_machine.current_index == Some(index)
+                        && !target.is_one_shot()
+                        && player.animation(index).is_some() =>
+                {
+                    if let Some(animation) = player.animation_mut(index) {
+                        animation.repeat().resume();
+                    }
+                }
+                Some(index) => {
+                    let animation = transitions.play(&mut player, index, state_machine.blend);
+                    if !target.is_one_shot() {
+                        animation.repeat();
+                    }
+                    animation.resume();
+                    state_machine.current_index = Some(index);
+                }
+                None => {
+                    // Without an idle clip, hold the first frame of the last one
+                    if let Some(animation) = state_machine
+                        .current_index
+                        .and_then(|index| player.animation_mut(index))
+                    {
+                        animation.pause().set_seek_time(0.0);
+                    }
+                }
+            }
+            state_machine.current = Some(target);
+        }
+
+        if !target.is_one_shot() {
+            if let Some(animation) = state_machine
+                .clip(target)
+                .and_then(|index| player.animation_mut(index))
+            {
+                animation.set_speed((speed / state_machine.reference_speed).clamp(0.5, 3.0));
+            }
+        }
+    }
+}
+
+fn observe_on_step(
+    trigger: Trigger<AnimationEvent>,
+    transform_q: Query<&Transform>,
+    mut emitters: ParticleEmitters,
+    mut game_rng: ResMut<GameRng>,
+) {
+    if trigger.event().0 != AnimationEventKind::Footstep {
+        return;
+    }
+    if let Ok(root_t) = transform_q.get(trigger.entity()) {
+        emitters.spawn(
+            ParticlePreset::FootstepDust,
+            root_t,
+            None,
+            game_rng.cosmetic(),
+        );
     }
 }
@@ -0,0 +1,180 @@
+use crate::game::effects::hook::ItemIsHooked;
+use crate::game::item::{ItemIsStomped, ItemPickup, ItemPickupCountry};
+use crate::game::movement::{MovementSet, MovementSettings};
+use crate::game::player::{CartCollider, ItemCaught, Player};
+use crate::state::InGameState;
+use bevy::app::App;
+use bevy::math::Vec3;
+use bevy::prelude::{
+    in_state, ChildBuild, ChildBuilder, Commands, Component, Entity, EventWriter, FixedUpdate, Has,
+    IntoSystemConfigs, Plugin, Query, Transform, Vec3Swizzles, With, Without,
+};
+use bevy_rapier3d::prelude::{Collider, ExternalImpulse, ReadMassProperties, Velocity};
+
+pub struct CartPlugin;
+impl Plugin for CartPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (track_items_in_cart, spill_items_on_impact)
+                .chain()
+                .after(MovementSet)
+                .run_if(in_state(InGameState::Playing)),
+        );
+    }
+}
+
+/// Top of the basket floor, in the cart's local space
+pub const BASKET_FLOOR_HEIGHT: f32 = 0.6;
+const BASKET_WALL_HEIGHT: f32 = 0.5;
+const BASKET_HALF_WIDTH: f32 = 0.5;
+const BASKET_HALF_LENGTH: f32 = 0.75;
+const BASKET_CENTER_Z: f32 = -1.25;
+const BASKET_WALL_THICKNESS: f32 = 0.03;
+
+/// Marks an item resting in the player's basket. Removed again if it falls or spills out.
+#[derive(Component)]
+pub struct ItemInCart;
+
+/// What the cart is carrying, updated every fixed tick.
+#[derive(Component, Default)]
+pub struct CartLoad {
+    /// Combined mass of every item in the basket
+    pub mass: f32,
+    pub items: usize,
+    /// Velocity going into the latest physics step, before any impact slowed the cart
+    pub last_linvel: Vec3,
+}
+
+/// Frame, floor and walls of the basket. Items are held in by physics, so they can still spill.
+pub fn spawn_cart_basket(parent: &mut ChildBuilder) {
+    let floor_half_height = 0.05;
+    let frame_half_height = (BASKET_FLOOR_HEIGHT - 2.0 * floor_half_height) / 2.0;
+    parent.spawn((
+        Collider::cuboid(BASKET_HALF_WIDTH, frame_half_height, BASKET_HALF_LENGTH),
+        Transform::from_xyz(0.0, frame_half_height, BASKET_CENTER_Z),
+        CartCollider,
+    ));
+    parent.spawn((
+        Collider::cuboid(BASKET_HALF_WIDTH, floor_half_height, BASKET_HALF_LENGTH),
+        Transform::from_xyz(
+            0.0,
+            BASKET_FLOOR_HEIGHT - floor_half_height,
+            BASKET_CENTER_Z,
+        ),
+        CartCollider,
+    ));
+    let wall_y = BASKET_FLOOR_HEIGHT + BASKET_WALL_HEIGHT / 2.0;
+    for side in [-1.0, 1.0] {
+        parent.spawn((
+            Collider::cuboid(
+                BASKET_WALL_THICKNESS,
+                BASKET_WALL_HEIGHT / 2.0,
+                BASKET_HALF_LENGTH,
+            ),
+            Transform::from_xyz(
+                side * (BASKET_HALF_WIDTH - BASKET_WALL_THICKNESS),
+                wall_y,
+                BASKET_CENTER_Z,
+            ),
+            CartCollider,
+        ));
+        parent.spawn((
+            Collider::cuboid(
+                BASKET_HALF_WIDTH,
+                BASKET_WALL_HEIGHT / 2.0,
+                BASKET_WALL_THICKNESS,
+            ),
+            Transform::from_xyz(
+                0.0,
+                wall_y,
+                BASKET_CENTER_Z + side * (BASKET_HALF_LENGTH - BASKET_WALL_THICKNESS),
+            ),
+            CartCollider,
+        ));
+    }
+}
+
+fn in_basket(local: Vec3) -> bool {
+    local.x.abs() < BASKET_HALF_WIDTH
+        && (local.z - BASKET_CENTER_Z).abs() < BASKET_HALF_LENGTH
+        // Allow items piled above the walls
+        && (BASKET_FLOOR_HEIGHT - 0.05..BASKET_FLOOR_HEIGHT + 2.0 * BASKET_WALL_HEIGHT)
+            .contains(&local.y)
+}
+
+fn track_items_in_cart(
+    mut commands: Commands,
+    mut player_q: Query<(&Transform, &mut CartLoad), With<Player>>,
+    item_q: Query<
+        (
+            Entity,
+            &Transform,
+            &ReadMassProperties,
+            &ItemPickupCountry,
+            Has<ItemInCart>,
+            Has<ItemIsStomped>,
+            Has<ItemIsHooked>,
+        ),
+        (With<ItemPickup>, Without<Player>),
+    >,
+    mut caught_events: EventWriter<ItemCaught>,
+) {
+    let Ok((player_t, mut load)) = player_q.get_single_mut() else {
+        return;
+    };
+    let to_cart = player_t.compute_affine().inverse();
+    let mut mass = 0.0;
+    let mut items = 0;
+    for (item, item_t, item_mass, item_country, was_in_cart, stomped, hooked) in item_q.iter() {
+        let inside = in_basket(to_cart.transform_point3(item_t.translation));
+        if inside {
+            mass += item_mass.get().mass;
+            items += 1;
+        }
+        if inside && !was_in_cart {
+            let mut item_ec = commands.entity(item);
+            item_ec.insert(ItemInCart);
+            if hooked {
+                // Let go so it settles in the basket
+                item_ec.remove::<ItemIsHooked>();
+            }
+            if stomped {
+                item_ec.remove::<ItemIsStomped>();
+            }
+            caught_events.send(ItemCaught {
+                item,
+                score: item_country.scores(),
+                stomped,
+            });
+        } else if !inside && was_in_cart {
+            commands.entity(item).remove::<ItemInCart>();
+        }
+    }
+    load.mass = mass;
+    load.items = items;
+}
+
+fn spill_items_on_impact(
+    mut player_q: Query<(&Velocity, &MovementSettings, &mut CartLoad), With<Player>>,
+    mut item_q: Query<
+        (&ReadMassProperties, &mut ExternalImpulse),
+        (With<ItemInCart>, Without<Player>),
+    >,
+) {
+    let Ok((velocity, player_ms, mut load)) = player_q.get_single_mut() else {
+        return;
+    };
+    let last_linvel = load.last_linvel;
+    load.last_linvel = velocity.linvel;
+    let impact = last_linvel.xz().length() - velocity.linvel.xz().length();
+    if impact < player_ms.spill_impact_speed {
+        return;
+    }
+    // Everything keeps going the way the cart was, and the jolt throws it up over the walls
+    let throw =
+        last_linvel.with_y(0.0) * player_ms.spill_carry + Vec3::Y * impact * player_ms.spill_lift;
+    for (item_mass, mut item_impulse) in item_q.iter_mut() {
+        item_impulse.impulse += throw * item_mass.get().mass;
+    }
+}
@@ -0,0 +1,172 @@
+use crate::game::cart::ItemInCart;
+use crate::game::effects::particles::{ParticleEmitters, ParticlePreset};
+use crate::game::game::ScoreResource;
+use crate::game::item::{ItemPickup, ItemPickupCountry};
+use crate::game::map::checkout::CheckoutCounter;
+use crate::game::map::ShopObject;
+use crate::game::movement::MovementSet;
+use crate::game::player::Player;
+use crate::game::rng::GameRng;
+use crate::state::InGameState;
+use bevy::app::App;
+use bevy::math::Vec3;
+use bevy::prelude::{
+    in_state, AssetServer, Commands, Component, DespawnRecursiveExt, Entity, Event, EventWriter,
+    FixedUpdate, IntoSystemConfigs, Name, Plugin, Query, Reflect, ReflectResource, Res, ResMut,
+    Resource, Time, Timer, TimerMode, Transform, With, Without,
+};
+use bevy_egui::EguiPlugin;
+use bevy_inspector_egui::prelude::ReflectInspectorOptions;
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+use bevy_inspector_egui::InspectorOptions;
+
+pub struct CheckoutPlugin;
+impl Plugin for CheckoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (scan_cart_items)
+                .after(MovementSet)
+                .run_if(in_state(InGameState::Playing)),
+        );
+        app.add_event::<ItemScanned>();
+        app.add_event::<RoundFinished>();
+        app.insert_resource(CheckoutResource { scan_secs: 0.35 });
+        app.init_resource::<Receipt>();
+        app.register_type::<CheckoutResource>();
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(ResourceInspectorPlugin::<CheckoutResource>::default());
+        }
+    }
+}
+
+#[derive(Resource, InspectorOptions, Reflect)]
+#[reflect(Resource, InspectorOptions)]
+pub struct CheckoutResource {
+    scan_secs: f32,
+}
+
+/// Where the cart stops to be scanned, relative to the counter
+pub const CHECKOUT_LANE_OFFSET: Vec3 = Vec3::new(1.5, 0.0, 0.0);
+const CHECKOUT_LANE_HALF_EXTENTS: Vec3 = Vec3::new(1.0, 1.0, 1.5);
+const CHECKOUT_SCANNER_OFFSET: Vec3 = Vec3::new(0.2, 1.1, 0.6);
+
+#[derive(Component)]
+pub struct Checkout {
+    scan_timer: Timer,
+}
+
+/// Sent for each item as it's scanned, with its tariff or bonus applied.
+#[derive(Event)]
+pub struct ItemScanned {
+    pub item: Entity,
+    pub score: i32,
+}
+
+/// Sent once the last item in the basket has been scanned.
+#[derive(Event)]
+pub struct RoundFinished {
+    pub total: i32,
+}
+
+pub struct ReceiptLine {
+    pub country: &'static str,
+    pub score: i32,
+}
+
+/// Everything scanned on this visit to the checkout. Cleared when the cart leaves the lane.
+#[derive(Resource, Default)]
+pub struct Receipt {
+    pub lines: Vec<ReceiptLine>,
+    pub finished: bool,
+}
+impl Receipt {
+    pub fn total(&self) -> i32 {
+        self.lines.iter().map(|line| line.score).sum()
+    }
+}
+
+pub fn spawn_checkout(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    transform: Transform,
+) -> Entity {
+    let checkout = CheckoutCounter.spawn(commands, asset_server);
+    commands.entity(checkout).insert((
+        Name::new("Checkout"),
+        Checkout {
+            scan_timer: Timer::from_seconds(0.0, TimerMode::Once),
+        },
+        transform,
+    ));
+    checkout
+}
+
+fn scan_cart_items(
+    mut commands: Commands,
+    player_q: Query<&Transform, With<Player>>,
+    mut checkout_q: Query<(&Transform, &mut Checkout), Without<Player>>,
+    item_q: Query<(Entity, &ItemPickupCountry), (With<ItemPickup>, With<ItemInCart>)>,
+    checkout_settings: Res<CheckoutResource>,
+    mut receipt: ResMut<Receipt>,
+    mut score_res: ResMut<ScoreResource>,
+    mut scanned_events: EventWriter<ItemScanned>,
+    mut finished_events: EventWriter<RoundFinished>,
+    mut emitters: ParticleEmitters,
+    mut game_rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let Ok(player_t) = player_q.get_single() else {
+        return;
+    };
+    let Some((checkout_t, mut checkout)) = checkout_q.iter_mut().find(|(checkout_t, _)| {
+        let local = checkout_t
+            .compute_affine()
+            .inverse()
+            .transform_point3(player_t.translation);
+        (local - CHECKOUT_LANE_OFFSET)
+            .abs()
+            .cmplt(CHECKOUT_LANE_HALF_EXTENTS)
+            .all()
+    }) else {
+        if receipt.finished {
+            *receipt = Receipt::default();
+        }
+        return;
+    };
+
+    if !checkout.scan_timer.tick(time.delta()).finished() {
+        return;
+    }
+    // Lowest entity first so scanning order doesn't depend on query order
+    let Some((item, item_country)) = item_q.iter().min_by_key(|(item, _)| *item) else {
+        if !receipt.lines.is_empty() && !receipt.finished {
+            receipt.finished = true;
+            finished_events.send(RoundFinished {
+                total: receipt.total(),
+            });
+        }
+        return;
+    };
+    if receipt.finished {
+        // Back for another trip
+        *receipt = Receipt::default();
+    }
+
+    // Negative scores are tariffs, positive ones bonuses
+    let score = item_country.scores();
+    score_res.score += score;
+    receipt.lines.push(ReceiptLine {
+        country: item_country.name(),
+        score,
+    });
+    commands.entity(item).despawn_recursive();
+    scanned_events.send(ItemScanned { item, score });
+    emitters.spawn(
+        ParticlePreset::ScanSparkle,
+        &Transform::from_translation(checkout_t.transform_point(CHECKOUT_SCANNER_OFFSET)),
+        None,
+        game_rng.cosmetic(),
+    );
+    checkout.scan_timer = Timer::from_seconds(checkout_settings.scan_secs, TimerMode::Once);
+}
@@ -1,5 +1,8 @@
-use crate::game::item::ItemPickup;
+use crate::game::animation::{AnimationState, PlayOneShotAnimation};
+use crate::game::input::{PlayerInput, PlayerInputLatch, PlayerInputSet};
+use crate::game::item::{ItemId, ItemPickup};
 use crate::game::player::Player;
+use crate::game::rng::GameRng;
 use crate::state::InGameState;
 use bevy::app::App;
 use bevy::input::ButtonInput;
@@ -7,12 +10,13 @@ use bevy::math::Vec3;
 use bevy::prelude::OnAdd;
 use bevy::prelude::Over;
 use bevy::prelude::{
-    in_state, Commands, Component, Entity, IntoSystemConfigs, MouseButton, Plugin, Query, Reflect,
-    Res, Resource, Time, Transform, Update, With,
+    in_state, Commands, Component, Entity, FixedUpdate, IntoSystemConfigs, MouseButton, Plugin,
+    Query, Reflect, Res, ResMut, Resource, Time, Transform, Update, With,
 };
 use bevy::prelude::{
     Click, Down, Pointer, ReflectResource, Timer, TimerMode, Trigger, Up, Without,
 };
+use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::prelude::ReflectInspectorOptions;
 use bevy_inspector_egui::quick::ResourceInspectorPlugin;
 use bevy_inspector_egui::InspectorOptions;
@@ -25,13 +29,21 @@ impl Plugin for PlayerSkillHookPlugin {
             Update,
             (move_hooked_items, shake_effect_system).run_if(in_state(InGameState::Playing)),
         );
+        app.add_systems(
+            FixedUpdate,
+            (handle_hook)
+                .after(PlayerInputSet)
+                .run_if(in_state(InGameState::Playing)),
+        );
         app.add_observer(setup_observers_on_added_item);
         app.insert_resource(HookResource {
             hook_range: 5.0,
             hooked_item_speed: 3.0,
         });
         app.register_type::<HookResource>();
-        app.add_plugins(ResourceInspectorPlugin::<HookResource>::default());
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(ResourceInspectorPlugin::<HookResource>::default());
+        }
     }
 }
 
@@ -70,48 +82,48 @@ pub fn setup_observers_on_added_item(trigger: Trigger<OnAdd, ItemPickup>, mut co
 
 pub fn hook_item_on_click(
     trigger: Trigger<Pointer<Down>>,
-    commands: Commands,
-    q_picked: Query<(Entity, &Transform), With<ItemPickup>>,
-    player_query: Query<&Transform, (With<Player>, Without<ItemIsHooked>)>,
-    hook_settings: Res<HookResource>,
+    q_picked: Query<&ItemId, With<ItemPickup>>,
+    latch: ResMut<PlayerInputLatch>,
 ) {
-    hook_item(
-        commands,
-        trigger.entity(),
-        q_picked,
-        player_query,
-        hook_settings,
-    );
+    latch_hook(trigger.entity(), q_picked, latch);
 }
 
 pub fn hook_item_on_drag(
     trigger: Trigger<Pointer<Over>>,
-    commands: Commands,
     mouse_input: Res<ButtonInput<MouseButton>>,
-    q_picked: Query<(Entity, &Transform), With<ItemPickup>>,
-    player_query: Query<&Transform, (With<Player>, Without<ItemIsHooked>)>,
-    hook_settings: Res<HookResource>,
+    q_picked: Query<&ItemId, With<ItemPickup>>,
+    latch: ResMut<PlayerInputLatch>,
 ) {
     if mouse_input.pressed(MouseButton::Left) {
-        hook_item(
-            commands,
-            trigger.entity(),
-            q_picked,
-            player_query,
-            hook_settings,
-        );
+        latch_hook(trigger.entity(), q_picked, latch);
     }
 }
 
-fn hook_item(
-    mut commands: Commands,
+// Hooks are latched by item id rather than entity, so a recorded run can find the same item
+fn latch_hook(
     triggering_entity: Entity,
-    q_picked: Query<(Entity, &Transform), With<ItemPickup>>,
-    player_query: Query<&Transform, (With<Player>, Without<ItemIsHooked>)>,
+    q_picked: Query<&ItemId, With<ItemPickup>>,
+    mut latch: ResMut<PlayerInputLatch>,
+) {
+    if let Ok(item_id) = q_picked.get(triggering_entity) {
+        latch.hook = Some(*item_id);
+    }
+}
+
+fn handle_hook(
+    mut commands: Commands,
+    input: Res<PlayerInput>,
+    q_picked: Query<(Entity, &Transform, &ItemId), With<ItemPickup>>,
+    player_query: Query<(Entity, &Transform), (With<Player>, Without<ItemIsHooked>)>,
     hook_settings: Res<HookResource>,
 ) {
-    if let Ok((entity, item_t)) = q_picked.get(triggering_entity) {
-        if let Ok(player_t) = player_query.get_single() {
+    if let Some(hook_target) = input.hook {
+        let picked = q_picked
+            .iter()
+            .find(|(_, _, item_id)| **item_id == hook_target);
+        if let (Some((entity, item_t, _)), Ok((player_e, player_t))) =
+            (picked, player_query.get_single())
+        {
             if item_t
                 .translation
                 .distance_squared(player_t.translation)
@@ -119,6 +131,7 @@ fn hook_item(
                 < hook_settings.hook_range.powi(2)
             {
                 commands.entity(entity).insert(ItemIsHooked);
+                commands.trigger_targets(PlayOneShotAnimation(AnimationState::HookThrow), player_e);
             } else {
                 commands
                     .entity(entity)
@@ -132,8 +145,9 @@ fn shake_effect_system(
     time: Res<Time>,
     mut commands: Commands,
     mut query: Query<(Entity, &mut Transform, &mut ShakeEffect)>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let mut rng = rand::thread_rng();
+    let rng = game_rng.cosmetic();
 
     for (entity, mut transform, mut shake) in query.iter_mut() {
         if shake.timer.tick(time.delta()).finished() {
@@ -152,11 +166,12 @@ fn shake_effect_system(
 fn move_hooked_items(
     time: Res<Time>,
     mut query: Query<(&mut Transform, Entity), With<ItemIsHooked>>,
-    player_query: Query<&Transform, (With<Player>, Without<ItemIsHooked>)>,
+    player_query: Query<(Entity, &Transform), (With<Player>, Without<ItemIsHooked>)>,
     hook_settings: Res<HookResource>,
 ) {
     if let Ok(player_t) = player_query.get_single() {
-        let forward_offset = Vec3::new(0.0, 0.5, -1.3);
+        // Drop into the basket
+        let forward_offset = Vec3::new(0.0, 1.0, -1.25);
         let rotated_offset = player_t.rotation * forward_offset;
         let target_position = player_t.translation + rotated_offset;
 
@@ -1,89 +1,580 @@
+use crate::quality::QualityPreset;
 use crate::state::InGameState;
 use bevy::app::{App, Update};
-use bevy::asset::{Assets, Handle};
-use bevy::color::palettes::basic::WHITE;
+use bevy::asset::io::Reader;
+use bevy::asset::{Asset, AssetApp, AssetLoader, AssetServer, Assets, Handle, LoadContext};
+use bevy::color::Color;
+use bevy::ecs::system::SystemParam;
 use bevy::math::Vec3;
-use bevy::pbr::{Material, MeshMaterial3d, StandardMaterial};
+use bevy::pbr::{MeshMaterial3d, StandardMaterial};
+use bevy::prelude::AlphaMode;
+use bevy::prelude::Sphere;
 use bevy::prelude::{
-    in_state, Command, Commands, Component, Entity, FloatExt, FromWorld, IntoSystemConfigs, Mesh,
-    Mesh3d, Plugin, Query, Res, Resource, Sphere, StableInterpolate, Time, Timer, TimerMode,
-    Transform, World,
+    in_state, Commands, Component, Cuboid, Cylinder, DetectChangesMut, Entity, IntoSystemConfigs,
+    Mesh, Mesh3d, Name, OnRemove, OnTransition, Plugin, Quat, Query, Res, Resource,
+    StableInterpolate, Time, Timer, TimerMode, Transform, Trigger, TypePath, Visibility, With,
 };
+use rand::Rng;
+use serde::Deserialize;
+use std::f32::consts::{PI, TAU};
+use std::fmt::{Display, Formatter};
 
 pub struct ParticlesPlugin;
 impl Plugin for ParticlesPlugin {
     fn build(&self, app: &mut App) {
+        app.init_asset::<ParticleEffect>();
+        app.register_asset_loader(ParticleEffectLoader);
+        app.init_resource::<ParticleBudget>();
+        app.add_systems(
+            OnTransition {
+                exited: InGameState::None,
+                entered: InGameState::Playing,
+            },
+            spawn_particle_emitters,
+        );
         app.add_systems(
             Update,
             (simulate_particles).run_if(in_state(InGameState::Playing)),
         );
+        app.add_observer(despawn_particle_pool);
     }
 }
 
-#[derive(Component)]
-pub struct Particle {
-    lifeteime_timer: Timer,
-    size: f32,
-    velocity: Vec3,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParticlePreset {
+    StompBurst,
+    FootstepDust,
+    VacuumStream,
+    ScanSparkle,
+}
+impl ParticlePreset {
+    pub const ALL: [ParticlePreset; 4] = [
+        ParticlePreset::StompBurst,
+        ParticlePreset::FootstepDust,
+        ParticlePreset::VacuumStream,
+        ParticlePreset::ScanSparkle,
+    ];
+
+    pub fn path(&self) -> &'static str {
+        match self {
+            ParticlePreset::StompBurst => "effects/stomp_burst.effect.ron",
+            ParticlePreset::FootstepDust => "effects/footstep_dust.effect.ron",
+            ParticlePreset::VacuumStream => "effects/vacuum_stream.effect.ron",
+            ParticlePreset::ScanSparkle => "effects/scan_sparkle.effect.ron",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ParticlePreset::StompBurst => "Stomp Particles",
+            ParticlePreset::FootstepDust => "Footstep Particles",
+            ParticlePreset::VacuumStream => "Vacuum Particles",
+            ParticlePreset::ScanSparkle => "Scan Particles",
+        }
+    }
+}
+
+/// Hard cap on live particles across every emitter. Emits past the cap are dropped.
+#[derive(Resource)]
+pub struct ParticleBudget {
+    pub max_particles: usize,
+}
+impl Default for ParticleBudget {
+    fn default() -> Self {
+        QualityPreset::default().particle_budget()
+    }
+}
+
+/// How an effect looks and moves, loaded from a `.effect.ron` file in `game-assets/effects/`.
+/// Positions and velocities are in the local space of the transform the effect is spawned at,
+/// where -Z is forward.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ParticleEffectDefinition {
+    /// Particles per spawn
+    pub count: usize,
+    /// Most particles alive at once for this effect
+    pub capacity: usize,
+    pub lifetime: (f32, f32),
+    pub size: (f32, f32),
+    /// Size multiplier keyed by lifetime fraction
+    pub size_over_life: Vec<(f32, f32)>,
+    pub spawn: ParticleSpawnShape,
+    pub velocity: ParticleVelocity,
+    /// How quickly particles slow down
+    pub drag: f32,
+    /// Downwards acceleration
+    #[serde(default)]
+    pub gravity: f32,
+    /// Bounce off a flat floor instead of falling through it
+    #[serde(default)]
+    pub ground: Option<ParticleGround>,
+    /// Point the mesh's Y axis along the particle's velocity
+    #[serde(default)]
+    pub align_to_velocity: bool,
+    /// sRGBA keyed by lifetime fraction. Any alpha below 1 blends the particles.
+    pub color_over_life: Vec<(f32, [f32; 4])>,
+    pub mesh: ParticleMesh,
+    #[serde(default)]
+    pub unlit: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ParticleGround {
+    #[serde(default)]
+    pub height: f32,
+    /// Fraction of vertical speed kept on each bounce
+    pub restitution: f32,
+    /// Fraction of horizontal speed lost on each bounce
+    #[serde(default)]
+    pub friction: f32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum ParticleSpawnShape {
+    /// Within a box around `offset`, optionally dropped to the ground
+    Box {
+        offset: [f32; 3],
+        half_extents: [f32; 3],
+        #[serde(default)]
+        on_ground: bool,
+    },
+    /// Along the line to the spawn target, plus noise
+    TowardTarget {
+        distance: (f32, f32),
+        noise: [f32; 3],
+    },
 }
 
-pub fn spawn_particle<M: Material>(
+#[derive(Deserialize, Debug, Clone)]
+pub enum ParticleVelocity {
+    /// Outwards in every direction with a random upwards component
+    Sphere { speed: f32, up: (f32, f32) },
+    /// Uniformly between two vectors
+    Range { min: [f32; 3], max: [f32; 3] },
+    /// Towards the spawn target
+    TowardTarget { speed: (f32, f32) },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub enum ParticleMesh {
+    Sphere { radius: f32 },
+    Cylinder { radius: f32, height: f32 },
+    Cuboid { size: [f32; 3] },
+}
+impl ParticleMesh {
+    fn mesh(&self) -> Mesh {
+        match *self {
+            ParticleMesh::Sphere { radius } => Sphere::new(radius).into(),
+            ParticleMesh::Cylinder { radius, height } => Cylinder::new(radius, height).into(),
+            ParticleMesh::Cuboid { size } => Cuboid::from_size(size.into()).into(),
+        }
+    }
+}
+
+// Colors over life are baked into this many materials so particles still share materials
+const COLOR_STEPS: usize = 8;
+
+#[derive(Asset, TypePath, Debug)]
+pub struct ParticleEffect {
+    pub definition: ParticleEffectDefinition,
     mesh: Handle<Mesh>,
-    material: Handle<M>,
-    translation: Vec3,
-    lifetime: f32,
+    materials: Vec<Handle<StandardMaterial>>,
+}
+impl ParticleEffect {
+    fn material_for(&self, fraction: f32) -> Handle<StandardMaterial> {
+        let step = (fraction * (self.materials.len() - 1) as f32).round() as usize;
+        self.materials[step.min(self.materials.len() - 1)].clone()
+    }
+}
+
+fn sample_range(rng: &mut impl Rng, (min, max): (f32, f32)) -> f32 {
+    if min < max {
+        rng.random_range(min..=max)
+    } else {
+        min
+    }
+}
+
+fn sample_curve<T: Copy>(curve: &[(f32, T)], t: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T> {
+    let (first, last) = (curve.first()?, curve.last()?);
+    if t <= first.0 {
+        return Some(first.1);
+    }
+    for window in curve.windows(2) {
+        let ((from_t, from), (to_t, to)) = (window[0], window[1]);
+        if t <= to_t {
+            let span = (to_t - from_t).max(f32::EPSILON);
+            return Some(lerp(from, to, (t - from_t) / span));
+        }
+    }
+    Some(last.1)
+}
+
+#[derive(Debug)]
+pub enum ParticleEffectLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    EmptyColorOverLife,
+}
+impl Display for ParticleEffectLoaderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParticleEffectLoaderError::Io(e) => write!(f, "Could not read particle effect: {e}"),
+            ParticleEffectLoaderError::Ron(e) => write!(f, "Invalid particle effect: {e}"),
+            ParticleEffectLoaderError::EmptyColorOverLife => {
+                write!(f, "Particle effect needs at least one color_over_life key")
+            }
+        }
+    }
+}
+impl std::error::Error for ParticleEffectLoaderError {}
+
+struct ParticleEffectLoader;
+impl AssetLoader for ParticleEffectLoader {
+    type Asset = ParticleEffect;
+    type Settings = ();
+    type Error = ParticleEffectLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<ParticleEffect, ParticleEffectLoaderError> {
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(ParticleEffectLoaderError::Io)?;
+        let definition: ParticleEffectDefinition =
+            ron::de::from_bytes(&bytes).map_err(ParticleEffectLoaderError::Ron)?;
+        if definition.color_over_life.is_empty() {
+            return Err(ParticleEffectLoaderError::EmptyColorOverLife);
+        }
+
+        let mesh = load_context.add_labeled_asset("mesh".to_string(), definition.mesh.mesh());
+        let steps = if definition.color_over_life.len() == 1 {
+            1
+        } else {
+            COLOR_STEPS
+        };
+        let alpha_mode = if definition
+            .color_over_life
+            .iter()
+            .any(|(_, color)| color[3] < 1.0)
+        {
+            AlphaMode::Blend
+        } else {
+            AlphaMode::Opaque
+        };
+        let materials = (0..steps)
+            .map(|step| {
+                let fraction = step as f32 / (steps - 1).max(1) as f32;
+                let [r, g, b, a] =
+                    sample_curve(&definition.color_over_life, fraction, |from, to, t| {
+                        std::array::from_fn(|i| from[i] + (to[i] - from[i]) * t)
+                    })
+                    .unwrap_or([1.0; 4]);
+                load_context.add_labeled_asset(
+                    format!("material{step}"),
+                    StandardMaterial {
+                        base_color: Color::srgba(r, g, b, a),
+                        unlit: definition.unlit,
+                        alpha_mode,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        Ok(ParticleEffect {
+            definition,
+            mesh,
+            materials,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["effect.ron"]
+    }
+}
+
+pub struct Particle {
+    lifetime_timer: Timer,
     size: f32,
+    transform: Transform,
     velocity: Vec3,
-) -> impl Command {
-    spawn_particle_t(
-        mesh,
-        material,
-        Transform {
-            translation,
-            scale: Vec3::splat(size),
-            ..Default::default()
-        },
-        lifetime,
-        velocity,
-    )
-}
-
-pub fn spawn_particle_t<M: Material>(
-    mesh: Handle<Mesh>,
-    material: Handle<M>,
-    t: Transform,
-    lifetime: f32,
-    velocity: Vec3,
-) -> impl Command {
-    move |world: &mut World| {
-        world.spawn((
-            Particle {
-                lifeteime_timer: Timer::from_seconds(lifetime, TimerMode::Once),
-                size: t.scale.x,
+}
+
+/// Simulates a buffer of particles and draws them with a pool of mesh entities that are
+/// reused rather than spawned and despawned per particle.
+#[derive(Component)]
+pub struct ParticleEmitter {
+    pub preset: ParticlePreset,
+    effect: Handle<ParticleEffect>,
+    particles: Vec<Particle>,
+    pending: Vec<Particle>,
+    pool: Vec<Entity>,
+}
+impl ParticleEmitter {
+    pub fn new(preset: ParticlePreset, effect: Handle<ParticleEffect>) -> Self {
+        ParticleEmitter {
+            preset,
+            effect,
+            particles: vec![],
+            pending: vec![],
+            pool: vec![],
+        }
+    }
+}
+
+#[derive(Component)]
+struct PooledParticle;
+
+/// Spawn particle effects from any system or observer.
+#[derive(SystemParam)]
+pub struct ParticleEmitters<'w, 's> {
+    emitter_q: Query<'w, 's, &'static mut ParticleEmitter>,
+    effects: Res<'w, Assets<ParticleEffect>>,
+}
+impl ParticleEmitters<'_, '_> {
+    /// Spawns one burst of a preset's effect at `origin`. Some effects also aim at `target`.
+    /// Particles are queued until the next simulation step, where the budget decides which
+    /// are kept.
+    pub fn spawn(
+        &mut self,
+        preset: ParticlePreset,
+        origin: &Transform,
+        target: Option<Vec3>,
+        rng: &mut impl Rng,
+    ) {
+        let Some(mut emitter) = self
+            .emitter_q
+            .iter_mut()
+            .find(|emitter| emitter.preset == preset)
+        else {
+            return;
+        };
+        let Some(effect) = self.effects.get(&emitter.effect) else {
+            return;
+        };
+        let definition = &effect.definition;
+        let target = target.unwrap_or(origin.translation + *origin.forward());
+        let to_target = (target - origin.translation).normalize_or_zero();
+        for _ in 0..definition.count {
+            let translation = match definition.spawn {
+                ParticleSpawnShape::Box {
+                    offset,
+                    half_extents,
+                    on_ground,
+                } => {
+                    let local = Vec3::from(offset)
+                        + Vec3::from(half_extents)
+                            * Vec3::new(
+                                rng.random_range(-1.0..=1.0),
+                                rng.random_range(-1.0..=1.0),
+                                rng.random_range(-1.0..=1.0),
+                            );
+                    let world = origin.transform_point(local);
+                    if on_ground {
+                        world.reject_from_normalized(Vec3::Y)
+                    } else {
+                        world
+                    }
+                }
+                ParticleSpawnShape::TowardTarget { distance, noise } => {
+                    origin.translation
+                        + to_target * sample_range(rng, distance)
+                        + Vec3::from(noise)
+                            * Vec3::new(
+                                rng.random_range(0.0..=1.0),
+                                rng.random_range(0.0..=1.0),
+                                rng.random_range(0.0..=1.0),
+                            )
+                }
+            };
+            let velocity = match definition.velocity {
+                ParticleVelocity::Sphere { speed, up } => {
+                    let theta = rng.random_range(0.0..TAU);
+                    let phi = rng.random_range(0.0..PI);
+                    let y = sample_range(rng, up);
+                    Vec3::new(phi.sin() * theta.cos(), y, phi.sin() * theta.sin()) * speed
+                }
+                ParticleVelocity::Range { min, max } => {
+                    let (min, max) = (Vec3::from(min), Vec3::from(max));
+                    origin.rotation
+                        * Vec3::new(
+                            sample_range(rng, (min.x, max.x)),
+                            sample_range(rng, (min.y, max.y)),
+                            sample_range(rng, (min.z, max.z)),
+                        )
+                }
+                ParticleVelocity::TowardTarget { speed } => to_target * sample_range(rng, speed),
+            };
+            let rotation = if definition.align_to_velocity {
+                Quat::from_rotation_arc(Vec3::Y, velocity.normalize_or(Vec3::Y))
+            } else {
+                Quat::IDENTITY
+            };
+            emitter.pending.push(Particle {
+                lifetime_timer: Timer::from_seconds(
+                    sample_range(rng, definition.lifetime),
+                    TimerMode::Once,
+                ),
+                size: sample_range(rng, definition.size),
+                transform: Transform::from_translation(translation).with_rotation(rotation),
                 velocity,
-            },
-            Mesh3d(mesh),
-            MeshMaterial3d(material),
-            t,
+            });
+        }
+    }
+}
+
+fn spawn_particle_emitters(mut commands: Commands, asset_server: Res<AssetServer>) {
+    for preset in ParticlePreset::ALL {
+        commands.spawn((
+            Name::new(preset.name()),
+            ParticleEmitter::new(preset, asset_server.load(preset.path())),
         ));
     }
 }
 
 fn simulate_particles(
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform, &mut Particle)>,
+    mut emitter_q: Query<&mut ParticleEmitter>,
+    mut pooled_q: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut Mesh3d,
+            &mut MeshMaterial3d<StandardMaterial>,
+        ),
+        With<PooledParticle>,
+    >,
+    effects: Res<Assets<ParticleEffect>>,
+    budget: Res<ParticleBudget>,
     time: Res<Time>,
 ) {
-    for (entity, mut transform, mut particle) in &mut query {
-        if particle.lifeteime_timer.tick(time.delta()).just_finished() {
-            commands.entity(entity).despawn();
-        } else {
-            transform.translation += particle.velocity * time.delta_secs();
-            transform.scale =
-                Vec3::splat(particle.size.lerp(0.0, particle.lifeteime_timer.fraction()));
+    for mut emitter in &mut emitter_q {
+        let Some(effect) = effects.get(&emitter.effect) else {
+            emitter.pending.clear();
+            continue;
+        };
+        let definition = &effect.definition;
+        emitter.particles.retain_mut(|particle| {
+            if particle.lifetime_timer.tick(time.delta()).finished() {
+                return false;
+            }
             particle
                 .velocity
-                .smooth_nudge(&Vec3::ZERO, 4.0, time.delta_secs());
+                .smooth_nudge(&Vec3::ZERO, definition.drag, time.delta_secs());
+            particle.velocity.y -= definition.gravity * time.delta_secs();
+            particle.transform.translation += particle.velocity * time.delta_secs();
+            if let Some(ground) = &definition.ground {
+                if particle.transform.translation.y < ground.height && particle.velocity.y < 0.0 {
+                    particle.transform.translation.y = ground.height;
+                    particle.velocity.y *= -ground.restitution;
+                    particle.velocity.x *= 1.0 - ground.friction;
+                    particle.velocity.z *= 1.0 - ground.friction;
+                }
+            }
+            true
+        });
+    }
+
+    let mut live: usize = emitter_q
+        .iter()
+        .map(|emitter| emitter.particles.len())
+        .sum();
+    for mut emitter in &mut emitter_q {
+        let ParticleEmitter {
+            effect,
+            particles,
+            pending,
+            pool,
+            ..
+        } = &mut *emitter;
+        let Some(effect) = effects.get(&*effect) else {
+            continue;
+        };
+        let definition = &effect.definition;
+
+        let room = definition
+            .capacity
+            .saturating_sub(particles.len())
+            .min(budget.max_particles.saturating_sub(live));
+        let admitted = pending.len().min(room);
+        particles.extend(pending.drain(..admitted));
+        pending.clear();
+        live += admitted;
+
+        for (index, particle) in particles.iter().enumerate() {
+            let fraction = particle.lifetime_timer.fraction();
+            let size = particle.size
+                * sample_curve(&definition.size_over_life, fraction, |from, to, t| {
+                    from + (to - from) * t
+                })
+                .unwrap_or(1.0);
+            let transform = particle.transform.with_scale(Vec3::splat(size));
+            let material = effect.material_for(fraction);
+            match pool.get(index) {
+                Some(&pooled_e) => {
+                    if let Ok((mut pooled_t, mut pooled_v, mut pooled_mesh, mut pooled_material)) =
+                        pooled_q.get_mut(pooled_e)
+                    {
+                        *pooled_t = transform;
+                        pooled_v.set_if_neq(Visibility::Inherited);
+                        pooled_mesh.set_if_neq(Mesh3d(effect.mesh.clone()));
+                        pooled_material.set_if_neq(MeshMaterial3d(material));
+                    }
+                }
+                None => {
+                    // The pool only grows until it reaches the effect's capacity
+                    pool.push(
+                        commands
+                            .spawn((
+                                PooledParticle,
+                                Mesh3d(effect.mesh.clone()),
+                                MeshMaterial3d(material),
+                                transform,
+                            ))
+                            .id(),
+                    );
+                }
+            }
+        }
+        for &pooled_e in &pool[particles.len()..] {
+            if let Ok((_, mut pooled_v, _, _)) = pooled_q.get_mut(pooled_e) {
+                pooled_v.set_if_neq(Visibility::Hidden);
+            }
+        }
+    }
+}
+
+fn despawn_particle_pool(
+    trigger: Trigger<OnRemove, ParticleEmitter>,
+    mut commands: Commands,
+    emitter_q: Query<&ParticleEmitter>,
+) {
+    if let Ok(emitter) = emitter_q.get(trigger.entity()) {
+        for &pooled_e in &emitter.pool {
+            if let Some(pooled_ec) = commands.get_entity(pooled_e) {
+                pooled_ec.despawn();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preset_effects_parse() {
+        for preset in ParticlePreset::ALL {
+            let path = format!("game-assets/{}", preset.path());
+            let data = std::fs::read_to_string(&path).expect(&path);
+            let definition: ParticleEffectDefinition =
+                ron::de::from_str(&data).unwrap_or_else(|e| panic!("{path}: {e}"));
+            assert!(!definition.color_over_life.is_empty(), "{path}");
         }
     }
 }
@@ -1,7 +1,10 @@
-use crate::game::effects::particles::spawn_particle;
+use crate::game::animation::{AnimationState, PlayOneShotAnimation};
+use crate::game::effects::particles::{ParticleEmitters, ParticlePreset};
 use crate::game::game::{ScoreResource, TrackedByKDTree};
+use crate::game::input::{PlayerInput, PlayerInputSet};
 use crate::game::item::{ItemIsStomped, ItemPickup, ItemPickupCountry};
-use crate::game::player::{CartCollider, Player, CART_HEIGHT};
+use crate::game::player::Player;
+use crate::game::rng::GameRng;
 use crate::state::InGameState;
 use bevy::app::App;
 use bevy::color::palettes::basic::WHITE;
@@ -22,6 +25,7 @@ use bevy::reflect::Reflect;
 use bevy::render::mesh::CircleMeshBuilder;
 use bevy::scene::SceneInstance;
 use bevy::utils::info;
+use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::prelude::*;
 use bevy_inspector_egui::quick::ResourceInspectorPlugin;
 use bevy_rapier3d::pipeline::CollisionEvent;
@@ -29,7 +33,7 @@ use bevy_rapier3d::prelude::CollisionEvent::Started;
 use bevy_rapier3d::prelude::{Collider, ExternalImpulse, Vect, Velocity};
 use bevy_spatial::kdtree::KDTree3;
 use bevy_spatial::SpatialAccess;
-use rand::{rng, Rng};
+use rand::Rng;
 use std::f32::consts::{PI, TAU};
 
 const GRAVITY: f32 = -9.81;
@@ -37,13 +41,14 @@ const GRAVITY: f32 = -9.81;
 pub struct PlayerSkillStompPlugin;
 impl Plugin for PlayerSkillStompPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (handle_stomp).run_if(in_state(InGameState::Playing)),
-        );
         app.add_systems(
             FixedUpdate,
-            (draw_landing_reticule, update_landing_reticule).run_if(in_state(InGameState::Playing)),
+            (
+                (handle_stomp).after(PlayerInputSet),
+                draw_landing_reticule,
+                update_landing_reticule,
+            )
+                .run_if(in_state(InGameState::Playing)),
         );
         app.add_event::<CollisionEvent>();
         app.add_observer(trigger_stomp_removed);
@@ -51,32 +56,14 @@ impl Plugin for PlayerSkillStompPlugin {
             stomp_distance: 5.0,
             stomp_away_force: -0.01,
             stomp_up_force: 0.08,
-            stomp_particles: 80,
             stomp_distance_falloff: 0.5,
         });
-        app.init_resource::<StompParticleAssets>();
         app.register_type::<StompResource>();
-        app.add_plugins(ResourceInspectorPlugin::<StompResource>::default());
-        app.init_resource::<ScoreResource>();
-    }
-}
-
-#[derive(Resource)]
-pub struct StompParticleAssets {
-    pub mesh: Handle<Mesh>,
-    pub material: Handle<StandardMaterial>,
-}
-impl FromWorld for StompParticleAssets {
-    fn from_world(world: &mut World) -> Self {
-        Self {
-            mesh: world.resource_mut::<Assets<Mesh>>().add(Sphere::new(10.0)),
-            material: world
-                .resource_mut::<Assets<StandardMaterial>>()
-                .add(StandardMaterial {
-                    base_color: WHITE.into(),
-                    ..Default::default()
-                }),
+        if app.is_plugin_added::<EguiPlugin>() {
+            // No egui to inspect with when running headless
+            app.add_plugins(ResourceInspectorPlugin::<StompResource>::default());
         }
+        app.init_resource::<ScoreResource>();
     }
 }
 
@@ -86,7 +73,6 @@ pub struct StompResource {
     stomp_distance: f32,
     stomp_away_force: f32,
     stomp_up_force: f32,
-    stomp_particles: i32,
     stomp_distance_falloff: f32,
 }
 
@@ -199,8 +185,8 @@ fn compute_landing_pos(initial_position: Vec3, initial_velocity: Vect) -> Option
 
 fn handle_stomp(
     mut commands: Commands,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut player_q: Query<&Transform, With<Player>>,
+    input: Res<PlayerInput>,
+    mut player_q: Query<(Entity, &Transform), With<Player>>,
     tree: Res<KDTree3<TrackedByKDTree>>,
     mut item_q: Query<
         (&Transform, &mut ExternalImpulse, &Velocity),
@@ -210,11 +196,13 @@ fn handle_stomp(
         (&Transform, &mut ExternalImpulse),
         (Without<Player>, Without<ItemPickup>),
     >,
-    particle: Res<StompParticleAssets>,
+    mut emitters: ParticleEmitters,
     stomp_settings: Res<StompResource>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    if keys.just_pressed(KeyCode::Space) {
-        if let Ok(player_t) = player_q.get_single_mut() {
+    if input.stomp {
+        if let Ok((player_e, player_t)) = player_q.get_single_mut() {
+            commands.trigger_targets(PlayOneShotAnimation(AnimationState::Stomp), player_e);
             for (pos, opt_entity) in
                 tree.within_distance(player_t.translation, stomp_settings.stomp_distance)
             {
@@ -264,11 +252,11 @@ fn handle_stomp(
                     }
                 }
             }
-            draw_stomp_particles(
-                &mut commands,
-                &player_t,
-                &particle,
-                stomp_settings.stomp_particles,
+            emitters.spawn(
+                ParticlePreset::StompBurst,
+                player_t,
+                None,
+                game_rng.cosmetic(),
             );
         }
     }
@@ -289,30 +277,3 @@ fn trigger_stomp_removed(
         }
     }
 }
-
-fn draw_stomp_particles(
-    mut commands: &mut Commands,
-    player_t: &Transform,
-    particle: &Res<StompParticleAssets>,
-    stomp_particles: i32,
-) {
-    let mut rng = rng();
-    for i in 1..stomp_particles {
-        let size = rng.random_range(0.01..0.03);
-        let particle_spawn = player_t.translation;
-        let theta = rng.random_range(0.0..TAU); // 0 to 2π
-        let phi = rng.random_range(0.0..PI); // 0 to π
-        let x = phi.sin() * theta.cos();
-        let y = rng.random_range(0.1..0.5);
-        let z = phi.sin() * theta.sin();
-        let direction = Vec3::new(x, y, z) * 20.0;
-        commands.queue(spawn_particle(
-            particle.mesh.clone(),
-            particle.material.clone(),
-            particle_spawn.reject_from_normalized(Vec3::Y),
-            rng.random_range(0.5..1.5),
-            size,
-            direction,
-        ));
-    }
-}
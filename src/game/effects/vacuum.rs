@@ -1,9 +1,9 @@
 use crate::camera::GameCamera;
-use crate::game::effects::particles::{spawn_particle, spawn_particle_t};
-use crate::game::effects::stomp::StompParticleAssets;
+use crate::game::effects::particles::{ParticleEmitters, ParticlePreset};
 use crate::game::game::TrackedByKDTree;
 use crate::game::item::{item_pickup_collision_groups, ItemPickup};
 use crate::game::player::Player;
+use crate::game::rng::GameRng;
 use crate::hierarchy::get_root_parent_entity;
 use bevy::app::App;
 use bevy::asset::{Assets, Handle};
@@ -15,8 +15,9 @@ use bevy::pbr::StandardMaterial;
 use bevy::prelude::{Camera, Color, GlobalTransform, Quat, Real, ReflectResource, Window};
 use bevy::prelude::{Commands, Cylinder, Dir3, FromWorld, Mesh, Sphere, World};
 use bevy::prelude::{
-    MouseButton, Plugin, Query, Reflect, Res, Resource, Transform, Update, With, Without,
+    MouseButton, Plugin, Query, Reflect, Res, ResMut, Resource, Transform, Update, With, Without,
 };
+use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::prelude::ReflectInspectorOptions;
 use bevy_inspector_egui::quick::ResourceInspectorPlugin;
 use bevy_inspector_egui::InspectorOptions;
@@ -26,7 +27,7 @@ use bevy_rapier3d::na::RealField;
 use bevy_rapier3d::prelude::{Collider, CollisionGroups, QueryFilter, ReadRapierContext};
 use bevy_spatial::kdtree::KDTree3;
 use bevy_spatial::SpatialAccess;
-use rand::{rng, Rng};
+use rand::Rng;
 use web_sys::js_sys::Math;
 
 pub struct PlayerSkillVacuumPlugin;
@@ -38,8 +39,9 @@ impl Plugin for PlayerSkillVacuumPlugin {
             suck_to_force: 0.005,
         });
         app.register_type::<VacuumResource>();
-        app.add_plugins(ResourceInspectorPlugin::<VacuumResource>::default());
-        app.init_resource::<VacuumParticleAssets>();
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(ResourceInspectorPlugin::<VacuumResource>::default());
+        }
     }
 }
 
@@ -50,30 +52,7 @@ pub struct VacuumResource {
     suck_to_force: f32,
 }
 
-#[derive(Resource)]
-pub struct VacuumParticleAssets {
-    pub mesh: Handle<Mesh>,
-    pub material: Handle<StandardMaterial>,
-}
-impl FromWorld for VacuumParticleAssets {
-    fn from_world(world: &mut World) -> Self {
-        Self {
-            mesh: world
-                .resource_mut::<Assets<Mesh>>()
-                .add(Cylinder::new(0.01, 0.05)),
-            material: world
-                .resource_mut::<Assets<StandardMaterial>>()
-                .add(StandardMaterial {
-                    base_color: Color::srgba(0.5, 0.5, 0.5, 0.5),
-                    unlit: true,
-                    ..Default::default()
-                }),
-        }
-    }
-}
-
 fn handle_click(
-    mut commands: Commands,
     mouse_input: Res<ButtonInput<MouseButton>>,
     tree: Res<KDTree3<TrackedByKDTree>>,
     mut player_q: Query<&Transform, With<Player>>,
@@ -86,7 +65,8 @@ fn handle_click(
     window_q: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform), With<GameCamera>>,
     vacuum_settings: Res<VacuumResource>,
-    particle_assets: Res<VacuumParticleAssets>,
+    mut emitters: ParticleEmitters,
+    mut game_rng: ResMut<GameRng>,
 ) {
     // if mouse_input.pressed(MouseButton::Left) {
     //     if let Ok(player_t) = player_q.get_single_mut() {
@@ -128,11 +108,11 @@ fn handle_click(
                                 ray.origin + ray.direction * toi.min(vacuum_settings.suck_distance);
                             let suck_area = Collider::cuboid(1.0, 0.25, 0.25);
                             let to_player = (player_t.translation - ray.origin).normalize_or_zero();
-                            spawn_vacuum_particles(
-                                &mut commands,
-                                &particle_assets,
-                                hit_point,
-                                target_position,
+                            emitters.spawn(
+                                ParticlePreset::VacuumStream,
+                                &Transform::from_translation(hit_point),
+                                Some(target_position),
+                                game_rng.cosmetic(),
                             );
                             let to_player_flat =
                                 Vec3::new(to_player.x, 0.0, to_player.z).normalize_or_zero();
@@ -168,31 +148,3 @@ fn handle_click(
         }
     }
 }
-
-fn spawn_vacuum_particles(
-    mut commands: &mut Commands,
-    particle: &Res<VacuumParticleAssets>,
-    from: Vec3,
-    to: Vec3,
-) {
-    let mut rng = rng();
-    let dir_to = (to - from).normalize_or_zero();
-    for _ in 0..10 {
-        let offset_distance = rng.random_range(0.5..1.5);
-        let offset_position = from + dir_to * offset_distance;
-        let noise = Vec3::new(
-            rng.random_range(0.0..2.0),
-            rng.random_range(0.0..0.5),
-            rng.random_range(0.0..0.5),
-        );
-        let t = Transform::from_translation(offset_position + noise).looking_at(to, Dir3::Y)
-            * Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2));
-        commands.queue(spawn_particle_t(
-            particle.mesh.clone(),
-            particle.material.clone(),
-            t,
-            rng.random_range(0.5..1.5),
-            dir_to * rng.random_range(2.0..4.0),
-        ));
-    }
-}
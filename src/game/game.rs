@@ -1,18 +1,29 @@
 use crate::camera::GameCamera;
 use crate::game::animation::{
-    setup_animation_graph, AnimationPlugin, AnimationToPlay, PlayerOnStep,
+    setup_animation_graph, AnimationPlugin, AnimationState, AnimationStateMachine,
 };
+use crate::game::cart::CartPlugin;
+use crate::game::checkout::CheckoutPlugin;
 use crate::game::effects::hook::PlayerSkillHookPlugin;
 use crate::game::effects::particles::ParticlesPlugin;
 use crate::game::effects::stomp::PlayerSkillStompPlugin;
 use crate::game::effects::vacuum::PlayerSkillVacuumPlugin;
 use crate::game::hud::HudPlugin;
-use crate::game::item::{ItemIsStomped, ItemPickup, ItemPickupCollider, ItemPickupCountry};
-use crate::game::map::misc_shelf::MiscShelf;
-use crate::game::map::wall::spawn_walls;
+use crate::game::input::PlayerInputPlugin;
+use crate::game::item::{ItemIsStomped, ItemPickup, ItemPickupCountry, ItemPlugin};
+use crate::game::loading::LoadingPlugin;
+use crate::game::map::generator::{spawn_store, StoreLayout};
+use crate::game::map::lighting::{spawn_lighting, LightBudget, LightingPlan};
 use crate::game::map::*;
+use crate::game::minimap::MinimapPlugin;
 use crate::game::movement::{MovementPlugin, MovementSettings};
 use crate::game::player::PlayerPlugin;
+use crate::game::recording::InputRecordingPlugin;
+use crate::game::replay::ReplayPlugin;
+use crate::game::rng::GameRngPlugin;
+use crate::game::scene_collider::{SceneColliderPlugin, SceneColliderShape, SceneColliders};
+use crate::game::stock::StockPlugin;
+use crate::hierarchy::get_root_parent_entity;
 use crate::state::{InGameState, TitleMenuState};
 use bevy::app::App;
 use bevy::color::palettes::css::ORANGE_RED;
@@ -21,13 +32,13 @@ use bevy::image::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamp
 use bevy::math::Affine2;
 use bevy::pbr::CascadeShadowConfigBuilder;
 use bevy::prelude::{
-    debug, default, in_state, info, light_consts, Added, AmbientLight, AnimationClip,
-    AnimationGraph, AnimationGraphHandle, AnimationNodeIndex, AnimationPlayer, AssetServer, Assets,
-    BuildChildren, Camera, ChildBuild, Children, Color, Commands, Component, Dir3,
-    DirectionalLight, Entity, EventReader, FixedUpdate, GlobalTransform, Handle, HierarchyQueryExt,
-    IntoSystemConfigs, Mesh, Mesh3d, MeshMaterial3d, Meshable, Name, OnEnter, Parent, PbrBundle,
-    Plane3d, Plugin, Quat, Query, Res, ResMut, Resource, SceneRoot, Sprite, SpriteBundle,
-    StandardMaterial, Transform, Trigger, Update, Vec2, Vec3, With, Without,
+    debug, default, in_state, info, light_consts, resource_exists, Added, AmbientLight,
+    AnimationClip, AnimationGraph, AnimationGraphHandle, AnimationNodeIndex, AnimationPlayer,
+    AssetServer, Assets, BuildChildren, Bundle, Camera, ChildBuild, Children, Color, Commands,
+    Component, Dir3, Entity, EventReader, FixedUpdate, GlobalTransform, Handle, HierarchyQueryExt,
+    Image, IntoSystemConfigs, Mesh, Mesh3d, MeshMaterial3d, Meshable, Name, OnEnter, OnTransition,
+    Parent, PbrBundle, Plane3d, Plugin, Query, Res, ResMut, Resource, SceneRoot, Sprite,
+    SpriteBundle, StandardMaterial, Transform, Trigger, Update, Vec2, Vec3, With, Without,
 };
 use bevy::render::mesh::skinning::SkinnedMesh;
 use bevy::scene::SceneInstanceReady;
@@ -40,13 +51,27 @@ use bevy_rapier3d::prelude::{
 };
 use bevy_rapier3d::rapier::prelude::{ColliderBuilder, InteractionGroups};
 use bevy_spatial::{AutomaticUpdate, SpatialStructure, TransformMode};
-use std::f32::consts::PI;
 use std::time::Duration;
 
+pub const FLOOR_TEXTURE_PATH: &str = "textures/tile.png";
+/// Props placed by hand around the entrance
+pub const PROP_SCENE_PATHS: [&str; 3] = [
+    "models/item_ca_cereal_shreddies.glb#Scene0",
+    "models/item_us_cereal_luckycharms.glb#Scene0",
+    "models/plant.glb#Scene0",
+];
+pub const AMERICAN_MODEL_PATH: &str = "models/american.glb";
+
 pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(InGameState::Playing), setup_scene);
+        app.add_systems(
+            OnTransition {
+                exited: InGameState::None,
+                entered: InGameState::Playing,
+            },
+            setup_scene.run_if(resource_exists::<StoreLayout>),
+        );
         app.add_systems(
             Update,
             (detect_item_landing_floor).run_if(in_state(InGameState::Playing)),
@@ -55,15 +80,27 @@ impl Plugin for GamePlugin {
         //     FixedUpdate,
         //     ().run_if(in_state(InGameState::Playing)),
         // );
+        app.init_resource::<GameMode>();
+        app.add_plugins(GameRngPlugin);
+        app.add_plugins(PlayerInputPlugin);
+        app.add_plugins(InputRecordingPlugin);
+        app.add_plugins(ItemPlugin);
         app.add_plugins(PlayerPlugin);
         app.add_plugins(MovementPlugin);
+        app.add_plugins(CartPlugin);
+        app.add_plugins(CheckoutPlugin);
         app.add_plugins(ParticlesPlugin);
         app.add_plugins(PlayerSkillStompPlugin);
         // app.add_plugins(PlayerSkillVacuumPlugin);
         app.add_plugins(PlayerSkillHookPlugin);
         app.add_plugins(HudPlugin);
+        app.add_plugins(MinimapPlugin);
         app.add_plugins(AnimationPlugin);
+        app.add_plugins(SceneColliderPlugin);
         app.add_plugins(MapPlugin);
+        app.add_plugins(LoadingPlugin);
+        app.add_plugins(StockPlugin);
+        app.add_plugins(ReplayPlugin);
         app.add_plugins(
             AutomaticUpdate::<TrackedByKDTree>::new().with_spatial_ds(SpatialStructure::KDTree3),
         );
@@ -80,6 +117,20 @@ pub struct American;
 #[derive(Component)]
 pub struct FloorTag;
 
+/// Seed for a run, recorded alongside input so a run can be played back.
+#[derive(Resource)]
+pub struct GameSeed(pub u64);
+
+/// How a run is scored. Set from the title menu.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    /// Fill the basket and check out, mess is penalised
+    #[default]
+    Shopping,
+    /// Mess is rewarded
+    Rampage,
+}
+
 #[derive(Resource)]
 pub struct ScoreResource {
     pub score: i32,
@@ -89,72 +140,52 @@ fn setup_scene(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
     asset_server: Res<AssetServer>,
+    layout: Res<StoreLayout>,
+    light_budget: Res<LightBudget>,
 ) {
     info!("scene setup");
+    let floor_size = 2.0 * layout.half_extents;
     // ground plane
-    let tile_image = asset_server.load_with_settings("textures/tile.png", |s: &mut _| {
-        *s = ImageLoaderSettings {
-            sampler: ImageSampler::Descriptor(ImageSamplerDescriptor {
-                // rewriting mode to repeat image,
-                address_mode_u: ImageAddressMode::Repeat,
-                address_mode_v: ImageAddressMode::Repeat,
-                ..default()
-            }),
-            ..default()
-        }
-    });
+    let tile_image = load_floor_texture(&asset_server);
     commands
         .spawn((
             Name::new("Floor"),
-            Mesh3d(meshes.add(Plane3d::default().mesh().size(25.0, 82.0))),
+            Mesh3d(meshes.add(Plane3d::default().mesh().size(floor_size.x, floor_size.y))),
             MeshMaterial3d(materials.add(StandardMaterial {
                 base_color_texture: Some(tile_image.clone()),
-                uv_transform: Affine2::from_scale(Vec2::new(25.0 / 5.0, 82.0 / 5.0)),
+                uv_transform: Affine2::from_scale(floor_size / 5.0),
                 ..default()
             })),
             FloorTag,
-            Transform::from_xyz(-1.5, 0.0, 0.0),
-        ))
-        .with_children(|parent| {
-            parent.spawn((
-                Collider::cuboid(12.5, 0.01, 41.0),
-                Transform::from_xyz(0.0, 0.0, 0.0),
-                ActiveEvents::COLLISION_EVENTS,
-                CollisionGroups::new(Group::GROUP_3, Group::GROUP_1 | Group::GROUP_2), // Collision events when items touch floor
-            ));
-        });
-    let shreddies = asset_server.load("models/item_ca_cereal_shreddies.glb#Scene0");
-    commands
-        .spawn((
-            Name::new("Cereal"),
-            SceneRoot(shreddies),
-            ItemPickup,
-            Transform::from_xyz(-2.0, 0.0, -2.0),
-        ))
-        .with_children(|parent| {
-            parent.spawn((
-                Collider::cuboid(0.1, 0.3, 0.1),
-                Transform::from_xyz(0.0, 0.3, 0.0),
-                ItemPickupCollider,
-            ));
-        });
-    let charms = asset_server.load("models/item_us_cereal_luckycharms.glb#Scene0");
-    commands
-        .spawn((
-            Name::new("Cereal"),
-            SceneRoot(charms),
-            ItemPickup,
-            Transform::from_xyz(-2.0, 0.0, -3.0),
+            Transform::from_xyz(layout.center.x, 0.0, layout.center.y),
         ))
-        .with_children(|parent| {
-            parent.spawn((
-                Collider::cuboid(0.1, 0.3, 0.1),
-                Transform::from_xyz(0.0, 0.3, 0.0),
-                ItemPickupCollider,
-            ));
-        });
-    let america = asset_server.load("models/american.glb#Scene0");
+        .with_child(floor_collider(layout.half_extents));
+    let shreddies = asset_server.load(PROP_SCENE_PATHS[0]);
+    commands.spawn((
+        Name::new("Cereal"),
+        SceneRoot(shreddies),
+        ItemPickup,
+        SceneColliders {
+            fallback: SceneColliderShape::ConvexHull,
+        },
+        Transform::from_xyz(-2.0, 0.0, -2.0),
+    ));
+    let charms = asset_server.load(PROP_SCENE_PATHS[1]);
+    commands.spawn((
+        Name::new("Cereal"),
+        SceneRoot(charms),
+        ItemPickup,
+        SceneColliders {
+            fallback: SceneColliderShape::ConvexHull,
+        },
+        Transform::from_xyz(-2.0, 0.0, -3.0),
+    ));
+    let america = asset_server.load(GltfAssetLabel::Scene(0).from_asset(AMERICAN_MODEL_PATH));
+    let (america_graph, america_idle) = AnimationGraph::from_clip(
+        asset_server.load(GltfAssetLabel::Animation(0).from_asset(AMERICAN_MODEL_PATH)),
+    );
     commands
         .spawn((
             Name::new("American"),
@@ -165,6 +196,8 @@ fn setup_scene(
                 angular_damping: 1.0,
             },
             American,
+            AnimationStateMachine::new(graphs.add(america_graph))
+                .with_clip(AnimationState::Idle, america_idle),
         ))
         .with_children(|parent| {
             parent.spawn((
@@ -172,8 +205,9 @@ fn setup_scene(
                 Transform::from_xyz(0.0, 1.0, 0.0),
             ));
         })
-        .observe(setup_ragdoll);
-    let plant = asset_server.load("models/plant.glb#Scene0");
+        .observe(setup_ragdoll)
+        .observe(setup_animation_graph);
+    let plant = asset_server.load(PROP_SCENE_PATHS[2]);
     commands
         .spawn((
             Name::new("Plant"),
@@ -186,81 +220,58 @@ fn setup_scene(
                 Transform::from_xyz(0.0, 0.75, 0.0),
             ));
         });
-    for i in -4..4 {
-        if i == 0 {
-            continue;
-        }
-        for j in -4..4 {
-            let shelf = MiscShelf.spawn(&mut commands, &asset_server);
-            commands.entity(shelf).insert(Transform::from_xyz(
-                3.0 * i as f32,
-                0.0,
-                -10.0 * j as f32,
-            ));
-        }
-    }
-    spawn_walls(
+    spawn_store(&mut commands, &asset_server, &layout);
+    spawn_lighting(
         &mut commands,
-        &asset_server,
-        Vec3::new(11.0, 0.0, 41.0),
-        Vec3::new(11.0, 0.0, -41.0),
-    )
-    .expect("failed to create walls");
-    spawn_walls(
-        &mut commands,
-        &asset_server,
-        Vec3::new(11.0, 0.0, 41.0),
-        Vec3::new(-14.0, 0.0, 41.0),
-    )
-    .expect("failed to create walls");
-    spawn_walls(
-        &mut commands,
-        &asset_server,
-        Vec3::new(-14.0, 0.0, 41.0),
-        Vec3::new(-14.0, 0.0, -41.0),
-    )
-    .expect("failed to create walls");
-    spawn_walls(
-        &mut commands,
-        &asset_server,
-        Vec3::new(-14.0, 0.0, -41.0),
-        Vec3::new(11.0, 0.0, -41.0),
-    )
-    .expect("failed to create walls");
-    commands.spawn((
-        DirectionalLight {
-            illuminance: 2_000.0,
-            shadows_enabled: true,
-            ..default()
-        },
-        Transform {
-            translation: Vec3::new(0.0, 10.0, 0.0),
-            rotation: Quat::from_rotation_x(-PI / 4.),
+        &LightingPlan::for_layout(&layout, &light_budget),
+    );
+}
+
+/// Tiles across the whole floor. Loaded with the same settings wherever it's loaded, since the
+/// first load's settings are the ones that stick.
+pub fn load_floor_texture(asset_server: &AssetServer) -> Handle<Image> {
+    asset_server.load_with_settings(FLOOR_TEXTURE_PATH, |s: &mut _| {
+        *s = ImageLoaderSettings {
+            sampler: ImageSampler::Descriptor(ImageSamplerDescriptor {
+                // rewriting mode to repeat image,
+                address_mode_u: ImageAddressMode::Repeat,
+                address_mode_v: ImageAddressMode::Repeat,
+                ..default()
+            }),
             ..default()
-        },
-    ));
+        }
+    })
+}
+
+pub fn floor_collider(half_extents: Vec2) -> impl Bundle {
+    (
+        Collider::cuboid(half_extents.x, 0.01, half_extents.y),
+        Transform::from_xyz(0.0, 0.0, 0.0),
+        ActiveEvents::COLLISION_EVENTS,
+        CollisionGroups::new(Group::GROUP_3, Group::GROUP_1 | Group::GROUP_2), // Collision events when items touch floor
+    )
 }
 
 fn detect_item_landing_floor(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
     parents_q: Query<&Parent>,
-    items_q: Query<(Entity), With<ItemPickup>>,
-    floor_q: Query<(Entity), With<FloorTag>>,
+    items_q: Query<(), With<ItemPickup>>,
+    floor_q: Query<(), With<FloorTag>>,
 ) {
     for event in collision_events.read() {
         if let CollisionEvent::Started(entity1, entity2, _) = event {
             let mut item_entity = None;
             let mut floor_entity = None;
 
+            // Colliders generated from a model sit on its mesh nodes, deep under the item
             for &entity in [entity1, entity2].iter() {
-                if let Ok(parent) = parents_q.get(*entity) {
-                    if let Ok(item_e) = items_q.get(**parent) {
-                        item_entity = Some(item_e);
-                    }
-                    if let Ok(floor_e) = floor_q.get(**parent) {
-                        floor_entity = Some(floor_e);
-                    }
+                let root = get_root_parent_entity(*entity, &parents_q);
+                if items_q.contains(root) {
+                    item_entity = Some(root);
+                }
+                if floor_q.contains(root) {
+                    floor_entity = Some(root);
                 }
             }
 
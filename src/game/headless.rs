@@ -0,0 +1,453 @@
+use crate::game::game::GamePlugin;
+use crate::game::recording::PlaybackFinished;
+use crate::state::{AppState, StatePlugin};
+use bevy::animation::{AnimationClip, AnimationGraph};
+use bevy::app::{App, AppExit};
+use bevy::asset::{AssetApp, AssetPlugin};
+use bevy::hierarchy::HierarchyPlugin;
+use bevy::image::Image;
+use bevy::pbr::StandardMaterial;
+use bevy::prelude::{
+    EventReader, EventWriter, Mesh, NextState, ResMut, Startup, TransformPlugin, Update,
+};
+use bevy::scene::ScenePlugin;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::MinimalPlugins;
+use bevy_rapier3d::prelude::{NoUserData, RapierPhysicsPlugin};
+use std::time::Duration;
+
+/// Each update advances time by exactly one fixed tick, so runs are repeatable.
+pub const HEADLESS_TICK: Duration = Duration::from_micros(15_625);
+
+/// The game without a window or renderer, for simulations and playing back recordings. The
+/// level is generated and loaded as it is in the game, but without a glTF loader every model
+/// fails, so fixtures are left with their hand placed colliders.
+pub fn headless_app() -> App {
+    let mut app = headless_plugins();
+    app.add_systems(Startup, start_headless_game);
+    app.add_systems(Update, exit_after_playback);
+    app
+}
+
+fn headless_plugins() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: String::from("game-assets"),
+            ..Default::default()
+        },
+        ScenePlugin,
+        StatesPlugin,
+        TransformPlugin,
+        HierarchyPlugin,
+        bevy::input::InputPlugin,
+        RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule(),
+    ));
+    // Resources the gameplay plugins create handles for, even though nothing is rendered
+    app.init_asset::<Mesh>();
+    app.init_asset::<StandardMaterial>();
+    app.init_asset::<Image>();
+    app.init_asset::<AnimationGraph>();
+    app.init_asset::<AnimationClip>();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(HEADLESS_TICK));
+    app.add_plugins(StatePlugin);
+    app.add_plugins(GamePlugin);
+    app
+}
+
+fn start_headless_game(mut app_state: ResMut<NextState<AppState>>) {
+    app_state.set(AppState::Loading);
+}
+
+fn exit_after_playback(
+    mut finished_events: EventReader<PlaybackFinished>,
+    mut exit_events: EventWriter<AppExit>,
+) {
+    if finished_events.read().count() > 0 {
+        exit_events.send(AppExit::Success);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::cart::ItemInCart;
+    use crate::game::checkout::{spawn_checkout, Receipt, CHECKOUT_LANE_OFFSET};
+    use crate::game::game::{floor_collider, FloorTag, ScoreResource};
+    use crate::game::input::PlayerInputLatch;
+    use crate::game::item::{ItemId, ItemPickup, ItemPickupCollider, ItemPickupCountry};
+    use crate::game::map::bakery_rack::BakeryRack;
+    use crate::game::map::generator::StoreLayout;
+    use crate::game::map::knock_over::{KnockedOver, MessResource};
+    use crate::game::map::wall::{
+        spawn_wall_path, spawn_walls, OpeningKind, WallOpening, WallPath,
+    };
+    use crate::game::map::{Category, ItemCategory, ShopObject, ShopObjectScene};
+    use crate::game::player::Player;
+    use crate::game::recording::InputMode;
+    use crate::game::stock::{RestockWave, StockBudget, StockStats};
+    use crate::state::InGameState;
+    use bevy::core::Name;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::input::ButtonInput;
+    use bevy::math::Vec2;
+    use bevy::prelude::{
+        AssetServer, BuildChildren, ChildBuild, Commands, Entity, KeyCode, OnTransition, Res,
+        State, Transform, Vec3, With,
+    };
+    use bevy_rapier3d::prelude::Collider;
+
+    /// Loading gives up after a minute of fixed ticks, so this is always enough to start
+    const MAX_LOADING_UPDATES: usize = 4000;
+
+    /// Straight into play on a bare floor without generating a store, so each test spawns only
+    /// what it needs
+    fn started_app() -> App {
+        let mut app = headless_plugins();
+        app.add_systems(Startup, |mut app_state: ResMut<NextState<AppState>>| {
+            app_state.set(AppState::InGame);
+        });
+        app.add_systems(
+            OnTransition {
+                exited: InGameState::None,
+                entered: InGameState::Playing,
+            },
+            spawn_bare_floor,
+        );
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(
+            *app.world().resource::<State<InGameState>>().get(),
+            InGameState::Playing
+        );
+        app
+    }
+
+    fn spawn_bare_floor(mut commands: Commands) {
+        commands
+            .spawn((
+                Name::new("Floor"),
+                FloorTag,
+                Transform::from_xyz(-1.5, 0.0, 0.0),
+            ))
+            .with_child(floor_collider(Vec2::new(12.5, 41.0)));
+    }
+
+    /// The headless game as run from the command line, playing back `recording`
+    fn store_app(name: &str, recording: &str) -> App {
+        let path = std::env::temp_dir().join(format!("headless-{name}.txt"));
+        std::fs::write(&path, recording).unwrap();
+        let mut app = headless_app();
+        app.insert_resource(InputMode::Playback(path));
+        app
+    }
+
+    fn run_until_playing(app: &mut App) {
+        for _ in 0..MAX_LOADING_UPDATES {
+            app.update();
+            if *app.world().resource::<State<InGameState>>().get() == InGameState::Playing {
+                return;
+            }
+        }
+        panic!("headless game never finished loading");
+    }
+
+    fn run_ticks(app: &mut App, ticks: usize) {
+        for _ in 0..ticks {
+            app.update();
+        }
+    }
+
+    fn spawn_item(app: &mut App, translation: Vec3, country: ItemPickupCountry) -> Entity {
+        app.world_mut()
+            .spawn((
+                Transform::from_translation(translation),
+                ItemPickup,
+                country,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Collider::cuboid(0.1, 0.1, 0.1),
+                    Transform::from_xyz(0.0, 0.1, 0.0),
+                    ItemPickupCollider,
+                ));
+            })
+            .id()
+    }
+
+    /// Runs `spawn` once with the app's commands, the way level setup would
+    fn spawn_with<T: 'static>(
+        app: &mut App,
+        mut spawn: impl FnMut(&mut Commands, &Res<AssetServer>) -> T + Send + Sync + 'static,
+    ) -> T {
+        app.world_mut()
+            .run_system_once(
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    spawn(&mut commands, &asset_server)
+                },
+            )
+            .unwrap()
+    }
+
+    fn spawn_bakery_rack(app: &mut App, transform: Transform) -> Entity {
+        spawn_with(app, move |commands, asset_server| {
+            let rack = BakeryRack.spawn(commands, asset_server);
+            commands.entity(rack).insert(transform);
+            rack
+        })
+    }
+
+    fn item_count(app: &mut App) -> usize {
+        app.world_mut()
+            .query_filtered::<Entity, With<ItemPickup>>()
+            .iter(app.world())
+            .count()
+    }
+
+    fn player_translation(app: &mut App) -> Vec3 {
+        app.world_mut()
+            .query_filtered::<&Transform, With<Player>>()
+            .single(app.world())
+            .translation
+    }
+
+    #[test]
+    fn stomp_within_distance_raises_items() {
+        let mut app = started_app();
+        let item = spawn_item(&mut app, Vec3::new(1.0, 0.0, 2.0), ItemPickupCountry::EU);
+        run_ticks(&mut app, 30);
+        let resting_y = app.world().get::<Transform>(item).unwrap().translation.y;
+
+        app.world_mut().resource_mut::<PlayerInputLatch>().stomp = true;
+        run_ticks(&mut app, 10);
+
+        let stomped_y = app.world().get::<Transform>(item).unwrap().translation.y;
+        assert!(
+            stomped_y > resting_y + 0.1,
+            "item should be launched, was {resting_y} now {stomped_y}"
+        );
+    }
+
+    #[test]
+    fn item_in_basket_is_scanned_at_checkout() {
+        let mut app = started_app();
+        run_ticks(&mut app, 10);
+        let above_basket = player_translation(&mut app) + Vec3::new(0.0, 2.0, -1.25);
+        let item = spawn_item(&mut app, above_basket, ItemPickupCountry::CA);
+        run_ticks(&mut app, 60);
+
+        assert!(
+            app.world().get::<ItemInCart>(item).is_some(),
+            "item should be held in the basket"
+        );
+        assert_eq!(app.world().resource::<ScoreResource>().score, 0);
+
+        let checkout_t =
+            Transform::from_translation(player_translation(&mut app) - CHECKOUT_LANE_OFFSET);
+        spawn_with(&mut app, move |commands, asset_server| {
+            spawn_checkout(commands, asset_server, checkout_t);
+        });
+        run_ticks(&mut app, 30);
+
+        let receipt = app.world().resource::<Receipt>();
+        assert!(
+            receipt.finished,
+            "scanning the last item should finish the round"
+        );
+        assert_eq!(receipt.total(), ItemPickupCountry::CA.scores());
+        assert_eq!(
+            app.world().resource::<ScoreResource>().score,
+            ItemPickupCountry::CA.scores()
+        );
+        assert_eq!(
+            item_count(&mut app),
+            0,
+            "checked out item should be removed"
+        );
+    }
+
+    #[test]
+    fn steering_left_turns_the_cart_left() {
+        let mut app = started_app();
+        let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.press(KeyCode::KeyW);
+        keys.press(KeyCode::KeyA);
+        run_ticks(&mut app, 120);
+
+        let forward = app
+            .world_mut()
+            .query_filtered::<&Transform, With<Player>>()
+            .single(app.world())
+            .forward();
+        assert!(forward.x < -0.2, "cart should face left, faces {forward:?}");
+        assert!(forward.y.abs() < 0.01, "cart should stay upright");
+    }
+
+    #[test]
+    fn ramming_a_rack_knocks_it_over() {
+        let mut app = started_app();
+        let rack = spawn_bakery_rack(&mut app, Transform::from_xyz(0.0, 0.0, -10.0));
+
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyW);
+        run_ticks(&mut app, 120);
+
+        assert!(
+            app.world().get::<KnockedOver>(rack).is_some(),
+            "rack should be knocked over"
+        );
+        assert_eq!(app.world().resource::<MessResource>().knocked_over, 1);
+        assert!(app.world().resource::<ScoreResource>().score < 0);
+    }
+
+    #[test]
+    fn walls_stop_the_cart() {
+        let mut app = started_app();
+        spawn_with(&mut app, |commands, asset_server| {
+            spawn_walls(
+                commands,
+                asset_server,
+                Vec3::new(-5.0, 0.0, -4.0),
+                Vec3::new(5.0, 0.0, -4.0),
+            )
+            .expect("failed to create walls");
+        });
+
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyW);
+        run_ticks(&mut app, 300);
+
+        let player_z = player_translation(&mut app).z;
+        assert!(
+            player_z > -4.0,
+            "cart drove through the wall to z = {player_z}"
+        );
+    }
+
+    #[test]
+    fn shelves_are_stocked_within_budget() {
+        let mut app = started_app();
+        app.world_mut().resource_mut::<StockBudget>().max_items = 20;
+        spawn_bakery_rack(&mut app, Transform::from_xyz(10.0, 0.0, 10.0));
+        run_ticks(&mut app, 5);
+
+        assert_eq!(item_count(&mut app), 20);
+        let stats = app.world().resource::<StockStats>();
+        assert_eq!(stats.total, 20);
+        assert_eq!(stats.by_category.get(&Category::Bakery), Some(&20));
+        assert_eq!(stats.by_country.values().sum::<usize>(), 20);
+    }
+
+    #[test]
+    fn taken_stock_is_restocked_in_a_wave() {
+        let mut app = started_app();
+        spawn_bakery_rack(&mut app, Transform::from_xyz(10.0, 0.0, 10.0));
+        run_ticks(&mut app, 5);
+        let stocked = item_count(&mut app);
+        assert_eq!(stocked, 28);
+
+        let taken: Vec<Entity> = app
+            .world_mut()
+            .query_filtered::<Entity, With<ItemPickup>>()
+            .iter(app.world())
+            .take(3)
+            .collect();
+        for item in taken {
+            app.world_mut().despawn(item);
+        }
+        run_ticks(&mut app, 1);
+        assert_eq!(app.world().resource::<StockStats>().total, stocked - 3);
+
+        app.world_mut().send_event(RestockWave);
+        run_ticks(&mut app, 2);
+        assert_eq!(item_count(&mut app), stocked);
+    }
+
+    fn drive_forward_into(app: &mut App, path: WallPath, ticks: usize) -> f32 {
+        spawn_with(app, move |commands, asset_server| {
+            spawn_wall_path(commands, asset_server, &path).expect("failed to create walls");
+        });
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyW);
+        run_ticks(app, ticks);
+        player_translation(app).z
+    }
+
+    #[test]
+    fn diagonal_walls_and_corners_stop_the_cart() {
+        let mut app = started_app();
+        // A V pointing away from the cart, so it's steered into the corner
+        let path = WallPath::open(vec![
+            Vec3::new(-6.0, 0.0, -1.0),
+            Vec3::new(0.0, 0.0, -4.0),
+            Vec3::new(6.0, 0.0, -1.0),
+        ]);
+        let player_z = drive_forward_into(&mut app, path, 300);
+        assert!(
+            player_z > -4.0,
+            "cart drove through the corner to z = {player_z}"
+        );
+    }
+
+    #[test]
+    fn doorways_let_the_cart_through() {
+        let mut app = started_app();
+        let path = WallPath::open(vec![Vec3::new(-5.0, 0.0, -4.0), Vec3::new(5.0, 0.0, -4.0)])
+            .with_opening(WallOpening {
+                run: 0,
+                center: 5.0,
+                width: 3.0,
+                kind: OpeningKind::Door,
+            });
+        let player_z = drive_forward_into(&mut app, path, 300);
+        assert!(player_z < -6.0, "cart stopped at z = {player_z}");
+    }
+
+    #[test]
+    fn playback_runs_in_the_generated_store() {
+        let mut app = store_app("playback", &format!("seed 42\n{}", "W\n".repeat(60)));
+        run_until_playing(&mut app);
+        let fixtures = app.world().resource::<StoreLayout>().fixtures.len();
+        let spawned = app
+            .world_mut()
+            .query_filtered::<Entity, With<ShopObjectScene>>()
+            .iter(app.world())
+            .count();
+        // And the checkout
+        assert_eq!(spawned, fixtures + 1);
+
+        let exited = (0..100).any(|_| {
+            app.update();
+            app.should_exit().is_some()
+        });
+        assert!(exited, "playback never finished");
+    }
+
+    #[test]
+    fn the_same_seed_stocks_the_same_items() {
+        let stocked_items = || {
+            let mut app = store_app("stock", "seed 7\n");
+            // Less than the store holds, so the budget has to pick which slots to fill
+            app.insert_resource(StockBudget { max_items: 50 });
+            run_until_playing(&mut app);
+            run_ticks(&mut app, 10);
+            let mut items: Vec<(ItemId, Category, ItemPickupCountry)> = app
+                .world_mut()
+                .query::<(&ItemId, &ItemCategory, &ItemPickupCountry)>()
+                .iter(app.world())
+                .map(|(id, category, country)| (*id, category.0, *country))
+                .collect();
+            items.sort_by_key(|(id, _, _)| id.0);
+            items
+        };
+        let first = stocked_items();
+        assert!(!first.is_empty());
+        assert_eq!(first, stocked_items());
+    }
+}
@@ -1,11 +1,14 @@
+use crate::game::checkout::Receipt;
 use crate::game::game::ScoreResource;
+use crate::game::map::knock_over::MessResource;
+use crate::game::rng::GameRng;
 use crate::state::InGameState;
 use bevy::app::App;
 use bevy::color::Color;
 use bevy::prelude::{
     in_state, AssetServer, BackgroundColor, BuildChildren, ChildBuild, Commands, Component,
-    IntoSystemConfigs, LinearRgba, Node, OnEnter, Parent, Plugin, PositionType, Query, Res, Text,
-    Update, Val, With, Without,
+    IntoSystemConfigs, LinearRgba, Node, OnEnter, OnTransition, Parent, Plugin, PositionType,
+    Query, Res, ResMut, Text, TextColor, UiRect, Update, Val, Visibility, With, Without,
 };
 use bevy::text::TextSpan;
 use rand::Rng;
@@ -13,24 +16,41 @@ use rand::Rng;
 pub struct HudPlugin;
 impl Plugin for HudPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(InGameState::Playing), setup_hud);
-        app.add_systems(Update, (update_hud).run_if(in_state(InGameState::Playing)));
+        app.add_systems(
+            OnTransition {
+                exited: InGameState::None,
+                entered: InGameState::Playing,
+            },
+            setup_hud,
+        );
+        app.add_systems(
+            Update,
+            (update_hud, update_mess_text, update_receipt).run_if(in_state(InGameState::Playing)),
+        );
     }
 }
 
 #[derive(Component)]
 pub struct HudScoreText;
 
+#[derive(Component)]
+struct HudMessText;
+
 #[derive(Component)]
 struct SendItMeter;
 
 #[derive(Component)]
 struct SendItText;
 
+#[derive(Component)]
+struct ReceiptText;
+
 fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .spawn((Text::new("Score: "), HudScoreText))
-        .with_child((TextSpan::default(), HudScoreText));
+        .with_child((TextSpan::default(), HudScoreText))
+        .with_child(TextSpan::new("\nKnocked over: "))
+        .with_child((TextSpan::default(), HudMessText));
     commands
         .spawn((
             Node {
@@ -67,7 +87,7 @@ fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
         SendItText,
     ));
     commands.spawn((
-        Text::new("WASD - Move\nSpace - Stomp\nLeft Click - Suck\nShift - Run\nRight Click + Scroll - Camera"),
+        Text::new("W/S - Push\nA/D - Steer\nSpace - Stomp\nLeft Click - Suck\nShift - Run\nCtrl - Handbrake\nRight Click + Scroll - Camera\nC - Camera Mode\nR - Replay"),
         Node {
             position_type: PositionType::Absolute,
             top: Val::Px(10.0),
@@ -75,6 +95,20 @@ fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..Default::default()
         },
     ));
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(30.0),
+            left: Val::Px(20.0),
+            padding: UiRect::all(Val::Px(10.0)),
+            ..Default::default()
+        },
+        BackgroundColor(Color::LinearRgba(LinearRgba::new(0.9, 0.9, 0.85, 0.9))),
+        TextColor(Color::BLACK),
+        Visibility::Hidden,
+        ReceiptText,
+    ));
     let version = option_env!("COMMIT_HASH");
     commands.spawn((
         Text::new(version.unwrap_or("dev")),
@@ -93,13 +127,14 @@ fn update_hud(
     mut send_it_meter_q: Query<(&Parent, &mut Node, &mut BackgroundColor), With<SendItMeter>>,
     mut node_q: Query<(&mut Node), (Without<SendItMeter>, Without<SendItText>)>,
     mut send_it_text: Query<&mut Node, (Without<SendItMeter>, With<SendItText>)>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     for mut span in &mut score_text_q {
         let score = score_res.score;
         **span = format!("{score:.2}");
     }
     let send_it_progress = score_res.score.clamp(0, 100) as f32;
-    let mut rng = rand::rng();
+    let rng = game_rng.cosmetic();
     let shake_intensity = send_it_progress / 2.0;
     let offset_x = rng.random_range(-shake_intensity..=shake_intensity);
     let offset_y = rng.random_range(-shake_intensity..=shake_intensity);
@@ -122,3 +157,40 @@ fn update_hud(
         }
     }
 }
+
+fn update_mess_text(
+    mess: Res<MessResource>,
+    mut mess_text_q: Query<&mut TextSpan, With<HudMessText>>,
+) {
+    for mut span in &mut mess_text_q {
+        **span = mess.knocked_over.to_string();
+    }
+}
+
+fn update_receipt(
+    receipt: Res<Receipt>,
+    mut receipt_text_q: Query<(&mut Text, &mut Visibility), With<ReceiptText>>,
+) {
+    if !receipt.is_changed() {
+        return;
+    }
+    let Ok((mut text, mut visibility)) = receipt_text_q.get_single_mut() else {
+        return;
+    };
+    if receipt.lines.is_empty() {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Inherited;
+    let mut lines = String::from("RECEIPT\n");
+    for line in &receipt.lines {
+        let kind = if line.score < 0 { "tariff" } else { "bonus" };
+        lines += &format!("{:<8} {:>4} {kind}\n", line.country, line.score);
+    }
+    if receipt.finished {
+        lines += &format!("TOTAL    {:>4}", receipt.total());
+    } else {
+        lines += "Scanning...";
+    }
+    **text = lines;
+}
@@ -0,0 +1,86 @@
+use crate::game::item::ItemId;
+use crate::game::recording::{InputMode, InputRecording};
+use crate::state::InGameState;
+use bevy::app::App;
+use bevy::input::ButtonInput;
+use bevy::prelude::{
+    in_state, FixedUpdate, IntoSystemConfigs, KeyCode, Plugin, Res, ResMut, Resource, SystemSet,
+    Update,
+};
+
+pub struct PlayerInputPlugin;
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (latch_player_input).run_if(in_state(InGameState::Playing)),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (collect_player_input)
+                .in_set(PlayerInputSet)
+                .run_if(in_state(InGameState::Playing)),
+        );
+        app.init_resource::<PlayerInput>();
+        app.init_resource::<PlayerInputLatch>();
+    }
+}
+
+/// Systems reading [`PlayerInput`] in `FixedUpdate` should run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerInputSet;
+
+/// Player actions for the current fixed tick, whether live, recorded or played back.
+#[derive(Resource, Default, Clone, Debug, PartialEq)]
+pub struct PlayerInput {
+    pub forward: bool,
+    pub back: bool,
+    pub left: bool,
+    pub right: bool,
+    pub run: bool,
+    pub handbrake: bool,
+    pub stomp: bool,
+    pub hook: Option<ItemId>,
+}
+// One-shot actions seen between fixed ticks, so they aren't missed or repeated
+#[derive(Resource, Default)]
+pub struct PlayerInputLatch {
+    pub stomp: bool,
+    pub hook: Option<ItemId>,
+}
+
+fn latch_player_input(keys: Res<ButtonInput<KeyCode>>, mut latch: ResMut<PlayerInputLatch>) {
+    if keys.just_pressed(KeyCode::Space) {
+        latch.stomp = true;
+    }
+}
+
+fn collect_player_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut latch: ResMut<PlayerInputLatch>,
+    mut input: ResMut<PlayerInput>,
+    input_mode: Res<InputMode>,
+    mut recording: ResMut<InputRecording>,
+) {
+    let live_input = PlayerInput {
+        forward: keys.pressed(KeyCode::KeyW),
+        back: keys.pressed(KeyCode::KeyS),
+        left: keys.pressed(KeyCode::KeyA),
+        right: keys.pressed(KeyCode::KeyD),
+        run: keys.pressed(KeyCode::ShiftLeft),
+        handbrake: keys.pressed(KeyCode::ControlLeft),
+        stomp: latch.stomp,
+        hook: latch.hook,
+    };
+    latch.stomp = false;
+    latch.hook = None;
+
+    *input = match *input_mode {
+        InputMode::Live => live_input,
+        InputMode::Record(_) => {
+            recording.ticks.push(live_input.clone());
+            live_input
+        }
+        InputMode::Playback(_) => recording.next_tick().unwrap_or_default(),
+    };
+}
@@ -1,11 +1,33 @@
 use crate::game::game::TrackedByKDTree;
+use crate::game::rng::GameRng;
+use crate::game::scene_collider::SceneCollider;
+use crate::hierarchy::get_root_parent_entity;
+use crate::state::AppState;
+use bevy::app::App;
 use bevy::prelude::RayCastPickable;
-use bevy::prelude::{Component, LinearRgba};
+use bevy::prelude::{
+    Commands, Component, Has, LinearRgba, OnAdd, OnExit, Parent, Plugin, Query, ResMut, Resource,
+    Transform, Trigger, With,
+};
+use bevy::utils::{FixedState, HashMap};
 use bevy_rapier3d::prelude::*;
 use rand::distr::StandardUniform;
 use rand::prelude::Distribution;
 use rand::Rng;
+use std::hash::BuildHasher;
 
+pub struct ItemPlugin;
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnExit(AppState::InGame), reset_item_spawn_counts);
+        app.init_resource::<ItemSpawnCounts>();
+        app.add_observer(assign_item_origin);
+        app.add_observer(assign_item_id);
+        app.add_observer(assign_item_scene_collider);
+    }
+}
+
+// ItemPickupCountry is assigned by `assign_item_origin` when not given at spawn
 #[derive(Component)]
 #[require(
     TrackedByKDTree,
@@ -13,10 +35,20 @@ use rand::Rng;
     ExternalImpulse,
     GravityScale,
     RigidBody,
-    ItemPickupCountry(item_origin_random)
+    ReadMassProperties
 )]
 pub struct ItemPickup;
 
+/// Names an item the same way every time a seed is played, so a recorded hook finds it again.
+/// Made from where the item spawned and how many spawned there before it, since the order
+/// items spawn in across the store follows query order, which isn't stable.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ItemId(pub u64);
+
+// Items spawned so far at each position, by position bits
+#[derive(Resource, Default)]
+struct ItemSpawnCounts(HashMap<[u32; 3], u32>);
+
 #[derive(Component)]
 #[require(
     ColliderMassProperties(item_pickup_mass),
@@ -27,7 +59,7 @@ pub struct ItemPickupCollider;
 #[derive(Component)]
 pub struct ItemIsStomped;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum ItemPickupCountry {
     USA,
     CA,
@@ -37,6 +69,15 @@ pub enum ItemPickupCountry {
     China,
 }
 impl ItemPickupCountry {
+    pub const ALL: [ItemPickupCountry; 6] = [
+        ItemPickupCountry::USA,
+        ItemPickupCountry::CA,
+        ItemPickupCountry::Mexico,
+        ItemPickupCountry::EU,
+        ItemPickupCountry::UK,
+        ItemPickupCountry::China,
+    ];
+
     pub fn asset_path(&self) -> &'static str {
         match self {
             ItemPickupCountry::USA => "images/fl_us.png",
@@ -47,6 +88,16 @@ impl ItemPickupCountry {
             ItemPickupCountry::China => "images/fl_cn.png",
         }
     }
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemPickupCountry::USA => "USA",
+            ItemPickupCountry::CA => "Canada",
+            ItemPickupCountry::Mexico => "Mexico",
+            ItemPickupCountry::EU => "EU",
+            ItemPickupCountry::UK => "UK",
+            ItemPickupCountry::China => "China",
+        }
+    }
     pub fn highlight_color(&self) -> LinearRgba {
         if self.scores() > 0 {
             LinearRgba::rgb(0.0, 0.1, 0.0)
@@ -90,6 +141,47 @@ pub fn item_pickup_collision_groups() -> CollisionGroups {
     )
 }
 
-fn item_origin_random() -> ItemPickupCountry {
-    rand::random()
+fn assign_item_origin(
+    trigger: Trigger<OnAdd, ItemPickup>,
+    mut commands: Commands,
+    item_q: Query<Has<ItemPickupCountry>, With<ItemPickup>>,
+    mut rng: ResMut<GameRng>,
+) {
+    if let Ok(false) = item_q.get(trigger.entity()) {
+        let country: ItemPickupCountry = rng.gameplay().random();
+        commands.entity(trigger.entity()).insert(country);
+    }
+}
+
+fn reset_item_spawn_counts(mut spawn_counts: ResMut<ItemSpawnCounts>) {
+    spawn_counts.0.clear();
+}
+
+fn assign_item_id(
+    trigger: Trigger<OnAdd, ItemPickup>,
+    mut commands: Commands,
+    item_q: Query<&Transform, With<ItemPickup>>,
+    mut spawn_counts: ResMut<ItemSpawnCounts>,
+) {
+    let Ok(item_t) = item_q.get(trigger.entity()) else {
+        return;
+    };
+    let position_bits = item_t.translation.to_array().map(f32::to_bits);
+    let count = spawn_counts.0.entry(position_bits).or_default();
+    let id = ItemId(FixedState.hash_one((position_bits, *count)));
+    *count += 1;
+    commands.entity(trigger.entity()).insert(id);
+}
+
+// Colliders generated from an item's model need the item's mass and collision groups
+fn assign_item_scene_collider(
+    trigger: Trigger<OnAdd, SceneCollider>,
+    mut commands: Commands,
+    parent_q: Query<&Parent>,
+    item_q: Query<(), With<ItemPickup>>,
+) {
+    let root = get_root_parent_entity(trigger.entity(), &parent_q);
+    if item_q.contains(root) {
+        commands.entity(trigger.entity()).insert(ItemPickupCollider);
+    }
 }
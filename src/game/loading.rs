@@ -0,0 +1,149 @@
+use crate::game::animation::AnimationEventTable;
+use crate::game::effects::particles::{ParticleEffect, ParticlePreset};
+use crate::game::game::{load_floor_texture, AMERICAN_MODEL_PATH, PROP_SCENE_PATHS};
+use crate::game::item::ItemPickupCountry;
+use crate::game::map::checkout::CheckoutCounter;
+use crate::game::map::generator::{generate_store_layout, StoreLayout};
+use crate::game::map::wall::{WALL_LIGHT_SCENE_PATH, WALL_SCENE_PATH};
+use crate::game::map::{Category, ShopObject};
+use crate::game::player::{CART_EVENTS_PATH, CART_MODEL_PATH};
+use crate::state::AppState;
+use bevy::app::App;
+use bevy::asset::{AssetServer, RecursiveDependencyLoadState, UntypedHandle};
+use bevy::gltf::GltfAssetLabel;
+use bevy::prelude::{
+    in_state, info, warn, AnimationClip, Commands, Image, IntoSystemConfigs, NextState, OnEnter,
+    OnExit, Plugin, Res, ResMut, Resource, Time, Timer, TimerMode, Update,
+};
+use bevy::scene::Scene;
+
+/// Gives up waiting after this long and starts anyway, whatever is left streams in while playing
+const LOADING_TIMEOUT_SECS: f32 = 60.0;
+
+pub struct LoadingPlugin;
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::Loading),
+            load_level_assets.after(generate_store_layout),
+        );
+        app.add_systems(Update, finish_loading.run_if(in_state(AppState::Loading)));
+        app.add_systems(OnExit(AppState::InGame), unload_level_assets);
+    }
+}
+
+/// Every asset a level spawns. Held from loading until the level is left, so nothing has to
+/// stream in while the player is driving.
+#[derive(Resource)]
+pub struct LevelAssets {
+    handles: Vec<UntypedHandle>,
+    timeout: Timer,
+}
+impl LevelAssets {
+    pub fn for_layout(layout: &StoreLayout, asset_server: &AssetServer) -> Self {
+        let mut scene_paths: Vec<&'static str> = layout
+            .fixtures
+            .iter()
+            .filter_map(|placed| placed.fixture.path())
+            .collect();
+        scene_paths.extend(CheckoutCounter.path());
+        scene_paths.extend([WALL_SCENE_PATH, WALL_LIGHT_SCENE_PATH]);
+        scene_paths.extend(PROP_SCENE_PATHS);
+        scene_paths.extend(Category::ALL.iter().map(|category| category.item_path()));
+        scene_paths.sort_unstable();
+        scene_paths.dedup();
+
+        let mut handles: Vec<UntypedHandle> = scene_paths
+            .into_iter()
+            .map(|path| asset_server.load::<Scene>(path).untyped())
+            .collect();
+        for model_path in [CART_MODEL_PATH, AMERICAN_MODEL_PATH] {
+            handles.push(
+                asset_server
+                    .load::<Scene>(GltfAssetLabel::Scene(0).from_asset(model_path))
+                    .untyped(),
+            );
+            handles.push(
+                asset_server
+                    .load::<AnimationClip>(GltfAssetLabel::Animation(0).from_asset(model_path))
+                    .untyped(),
+            );
+        }
+        handles.push(
+            asset_server
+                .load::<AnimationEventTable>(CART_EVENTS_PATH)
+                .untyped(),
+        );
+        handles.push(load_floor_texture(asset_server).untyped());
+        // Flags shown when items are stomped
+        handles.extend(
+            ItemPickupCountry::ALL
+                .iter()
+                .map(|country| asset_server.load::<Image>(country.asset_path()).untyped()),
+        );
+        handles.extend(
+            ParticlePreset::ALL
+                .iter()
+                .map(|preset| asset_server.load::<ParticleEffect>(preset.path()).untyped()),
+        );
+        LevelAssets {
+            handles,
+            timeout: Timer::from_seconds(LOADING_TIMEOUT_SECS, TimerMode::Once),
+        }
+    }
+
+    /// How many assets are done, including any that failed, out of the total
+    pub fn progress(&self, asset_server: &AssetServer) -> (usize, usize) {
+        let settled = self
+            .handles
+            .iter()
+            .filter(|handle| {
+                matches!(
+                    asset_server.get_recursive_dependency_load_state(handle.id()),
+                    Some(
+                        RecursiveDependencyLoadState::Loaded
+                            | RecursiveDependencyLoadState::Failed(_)
+                    )
+                )
+            })
+            .count();
+        (settled, self.handles.len())
+    }
+}
+
+fn load_level_assets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    layout: Res<StoreLayout>,
+) {
+    let level_assets = LevelAssets::for_layout(&layout, &asset_server);
+    info!("loading {} level assets", level_assets.handles.len());
+    commands.insert_resource(level_assets);
+}
+
+fn finish_loading(
+    mut level_assets: ResMut<LevelAssets>,
+    asset_server: Res<AssetServer>,
+    mut app_state: ResMut<NextState<AppState>>,
+    time: Res<Time>,
+) {
+    let (settled, total) = level_assets.progress(&asset_server);
+    if level_assets.timeout.tick(time.delta()).just_finished() {
+        warn!("gave up loading after {LOADING_TIMEOUT_SECS}s with {settled}/{total} assets");
+    } else if settled < total {
+        return;
+    }
+    for handle in level_assets.handles.iter() {
+        if let Some(RecursiveDependencyLoadState::Failed(error)) =
+            asset_server.get_recursive_dependency_load_state(handle.id())
+        {
+            // Spawned anyway, it just won't show up
+            warn!("level asset failed to load: {error}");
+        }
+    }
+    app_state.set(AppState::InGame);
+}
+
+fn unload_level_assets(mut commands: Commands) {
+    commands.remove_resource::<LevelAssets>();
+}
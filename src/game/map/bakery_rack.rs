@@ -0,0 +1,51 @@
+use crate::game::map::{Category, ShopObject};
+use bevy::prelude::{Bundle, Transform};
+use bevy_rapier3d::geometry::Group;
+use bevy_rapier3d::prelude::{Collider, CollisionGroups};
+
+/// Open wire rack with four trays of bread
+pub struct BakeryRack;
+
+const TRAY_HEIGHTS: [f32; 4] = [0.3, 0.7, 1.1, 1.5];
+
+impl ShopObject for BakeryRack {
+    fn categories(&self) -> Vec<(f32, Category)> {
+        vec![(1.0, Category::Bakery)]
+    }
+
+    fn colliders_with_transforms(&self) -> Vec<(Collider, Transform)> {
+        let mut colliders = vec![];
+        for (x, z) in [(-1.4, -0.4), (-1.4, 0.4), (1.4, -0.4), (1.4, 0.4)] {
+            colliders.push((
+                Collider::cuboid(0.02, 0.85, 0.02),
+                Transform::from_xyz(x, 0.85, z),
+            ));
+        }
+        for y in TRAY_HEIGHTS {
+            colliders.push((
+                Collider::cuboid(1.4, 0.015, 0.4),
+                Transform::from_xyz(0.0, y, 0.0),
+            ));
+        }
+        colliders
+    }
+    fn player_collider(&self) -> impl Bundle {
+        (
+            Collider::cuboid(1.45, 0.85, 0.45),
+            Transform::from_xyz(0.0, 0.85, 0.0),
+            CollisionGroups::new(Group::GROUP_4, Group::GROUP_1),
+        )
+    }
+    fn item_slots(&self) -> Vec<Transform> {
+        let mut slots = vec![];
+        for y in TRAY_HEIGHTS {
+            for x in -3..=3 {
+                slots.push(Transform::from_xyz(x as f32 * 0.4, y + 0.015, 0.0));
+            }
+        }
+        slots
+    }
+    fn path(&self) -> Option<&'static str> {
+        None
+    }
+}
@@ -0,0 +1,54 @@
+use crate::game::map::{Category, ShopObject};
+use bevy::prelude::{Bundle, Transform};
+use bevy_rapier3d::geometry::Group;
+use bevy_rapier3d::prelude::{Collider, CollisionGroups};
+
+/// Pallet with cans stacked into a pyramid on top. The cans are the stock, so it topples.
+pub struct CanPyramid;
+
+const LAYERS: i32 = 5;
+const CAN_SPACING: f32 = 0.22;
+
+impl ShopObject for CanPyramid {
+    fn categories(&self) -> Vec<(f32, Category)> {
+        vec![(1.0, Category::Canned)]
+    }
+
+    fn colliders_with_transforms(&self) -> Vec<(Collider, Transform)> {
+        vec![(
+            Collider::cuboid(0.6, 0.075, 0.6),
+            Transform::from_xyz(0.0, 0.075, 0.0),
+        )]
+    }
+    fn player_collider(&self) -> impl Bundle {
+        (
+            Collider::cuboid(0.6, 0.075, 0.6),
+            Transform::from_xyz(0.0, 0.075, 0.0),
+            CollisionGroups::new(Group::GROUP_4, Group::GROUP_1),
+        )
+    }
+    fn item_slots(&self) -> Vec<Transform> {
+        let mut slots = vec![];
+        for layer in 0..LAYERS {
+            let side = LAYERS - layer;
+            let start = -(side - 1) as f32 * CAN_SPACING / 2.0;
+            for x in 0..side {
+                for z in 0..side {
+                    slots.push(Transform::from_xyz(
+                        start + x as f32 * CAN_SPACING,
+                        0.15 + layer as f32 * 0.2,
+                        start + z as f32 * CAN_SPACING,
+                    ));
+                }
+            }
+        }
+        slots
+    }
+    fn knockable(&self) -> bool {
+        // The cans topple on their own
+        false
+    }
+    fn path(&self) -> Option<&'static str> {
+        None
+    }
+}
@@ -0,0 +1,47 @@
+use crate::game::map::{Category, ShopObject};
+use bevy::prelude::{Bundle, Transform};
+use bevy_rapier3d::geometry::Group;
+use bevy_rapier3d::prelude::{Collider, CollisionGroups};
+
+/// Counter with a conveyor and till. The cart is scanned from the lane along its +X side.
+pub struct CheckoutCounter;
+
+impl ShopObject for CheckoutCounter {
+    fn categories(&self) -> Vec<(f32, Category)> {
+        vec![]
+    }
+
+    fn colliders_with_transforms(&self) -> Vec<(Collider, Transform)> {
+        vec![
+            // Counter and conveyor
+            (
+                Collider::cuboid(0.4, 0.45, 1.4),
+                Transform::from_xyz(0.0, 0.45, 0.0),
+            ),
+            // Till
+            (
+                Collider::cuboid(0.2, 0.15, 0.2),
+                Transform::from_xyz(0.1, 1.05, -1.1),
+            ),
+            // Scanner
+            (
+                Collider::cuboid(0.15, 0.1, 0.15),
+                Transform::from_xyz(0.2, 1.0, 0.6),
+            ),
+        ]
+    }
+    fn player_collider(&self) -> impl Bundle {
+        (
+            Collider::cuboid(0.45, 0.6, 1.45),
+            Transform::from_xyz(0.0, 0.6, 0.0),
+            CollisionGroups::new(Group::GROUP_4, Group::GROUP_1),
+        )
+    }
+    fn knockable(&self) -> bool {
+        // Bolted down
+        false
+    }
+    fn path(&self) -> Option<&'static str> {
+        None
+    }
+}
@@ -0,0 +1,58 @@
+use crate::game::map::{Category, ShopObject};
+use bevy::prelude::{Bundle, Transform};
+use bevy_rapier3d::geometry::Group;
+use bevy_rapier3d::prelude::{Collider, CollisionGroups};
+
+/// Open-topped chest freezer, stocked down in the well
+pub struct ChestFreezer;
+
+impl ShopObject for ChestFreezer {
+    fn categories(&self) -> Vec<(f32, Category)> {
+        vec![(1.0, Category::Frozen)]
+    }
+
+    fn colliders_with_transforms(&self) -> Vec<(Collider, Transform)> {
+        vec![
+            // Base of the well
+            (
+                Collider::cuboid(1.4, 0.15, 0.4),
+                Transform::from_xyz(0.0, 0.15, 0.0),
+            ),
+            (
+                Collider::cuboid(1.45, 0.45, 0.05),
+                Transform::from_xyz(0.0, 0.45, 0.4),
+            ),
+            (
+                Collider::cuboid(1.45, 0.45, 0.05),
+                Transform::from_xyz(0.0, 0.45, -0.4),
+            ),
+            (
+                Collider::cuboid(0.05, 0.45, 0.35),
+                Transform::from_xyz(1.4, 0.45, 0.0),
+            ),
+            (
+                Collider::cuboid(0.05, 0.45, 0.35),
+                Transform::from_xyz(-1.4, 0.45, 0.0),
+            ),
+        ]
+    }
+    fn player_collider(&self) -> impl Bundle {
+        (
+            Collider::cuboid(1.5, 0.45, 0.5),
+            Transform::from_xyz(0.0, 0.45, 0.0),
+            CollisionGroups::new(Group::GROUP_4, Group::GROUP_1),
+        )
+    }
+    fn item_slots(&self) -> Vec<Transform> {
+        let mut slots = vec![];
+        for x in -5..=5 {
+            for z in [-0.15, 0.15] {
+                slots.push(Transform::from_xyz(x as f32 * 0.25, 0.3, z));
+            }
+        }
+        slots
+    }
+    fn path(&self) -> Option<&'static str> {
+        None
+    }
+}
@@ -0,0 +1,65 @@
+use crate::game::map::{Category, ShopObject};
+use bevy::prelude::{Bundle, Transform};
+use bevy_rapier3d::geometry::Group;
+use bevy_rapier3d::prelude::{Collider, CollisionGroups};
+
+/// Tall open-fronted fridge, faces +Z
+pub struct DairyCooler;
+
+const SHELF_HEIGHTS: [f32; 4] = [0.2, 0.65, 1.1, 1.55];
+
+impl ShopObject for DairyCooler {
+    fn categories(&self) -> Vec<(f32, Category)> {
+        vec![(0.7, Category::Dairy), (0.3, Category::Meat)]
+    }
+
+    fn colliders_with_transforms(&self) -> Vec<(Collider, Transform)> {
+        let mut colliders = vec![
+            // Back
+            (
+                Collider::cuboid(1.5, 1.0, 0.05),
+                Transform::from_xyz(0.0, 1.0, -0.4),
+            ),
+            // Sides
+            (
+                Collider::cuboid(0.03, 1.0, 0.45),
+                Transform::from_xyz(-1.47, 1.0, 0.0),
+            ),
+            (
+                Collider::cuboid(0.03, 1.0, 0.45),
+                Transform::from_xyz(1.47, 1.0, 0.0),
+            ),
+            // Canopy
+            (
+                Collider::cuboid(1.5, 0.05, 0.45),
+                Transform::from_xyz(0.0, 1.95, 0.0),
+            ),
+        ];
+        for y in SHELF_HEIGHTS {
+            colliders.push((
+                Collider::cuboid(1.44, 0.02, 0.35),
+                Transform::from_xyz(0.0, y, -0.05),
+            ));
+        }
+        colliders
+    }
+    fn player_collider(&self) -> impl Bundle {
+        (
+            Collider::cuboid(1.5, 1.0, 0.5),
+            Transform::from_xyz(0.0, 1.0, 0.0),
+            CollisionGroups::new(Group::GROUP_4, Group::GROUP_1),
+        )
+    }
+    fn item_slots(&self) -> Vec<Transform> {
+        let mut slots = vec![];
+        for y in SHELF_HEIGHTS {
+            for x in -4..=4 {
+                slots.push(Transform::from_xyz(x as f32 * 0.3, y + 0.02, 0.05));
+            }
+        }
+        slots
+    }
+    fn path(&self) -> Option<&'static str> {
+        None
+    }
+}
@@ -0,0 +1,54 @@
+use crate::game::map::{Category, ShopObject};
+use bevy::prelude::{Bundle, Transform};
+use bevy_rapier3d::geometry::Group;
+use bevy_rapier3d::prelude::{Collider, CollisionGroups};
+
+/// Promotional display at the end of an aisle, stepped so the lower tiers stick out
+pub struct EndCap;
+
+impl ShopObject for EndCap {
+    fn categories(&self) -> Vec<(f32, Category)> {
+        vec![(0.6, Category::Beverages), (0.4, Category::Snacks)]
+    }
+
+    fn colliders_with_transforms(&self) -> Vec<(Collider, Transform)> {
+        vec![
+            (
+                Collider::cuboid(1.5, 0.2, 0.45),
+                Transform::from_xyz(0.0, 0.2, 0.0),
+            ),
+            (
+                Collider::cuboid(1.5, 0.2, 0.3),
+                Transform::from_xyz(0.0, 0.6, -0.15),
+            ),
+            (
+                Collider::cuboid(1.5, 0.2, 0.15),
+                Transform::from_xyz(0.0, 1.0, -0.3),
+            ),
+            // Sign board
+            (
+                Collider::cuboid(1.5, 0.4, 0.02),
+                Transform::from_xyz(0.0, 1.6, -0.43),
+            ),
+        ]
+    }
+    fn player_collider(&self) -> impl Bundle {
+        (
+            Collider::cuboid(1.5, 1.0, 0.5),
+            Transform::from_xyz(0.0, 1.0, 0.0),
+            CollisionGroups::new(Group::GROUP_4, Group::GROUP_1),
+        )
+    }
+    fn item_slots(&self) -> Vec<Transform> {
+        let mut slots = vec![];
+        for (y, z) in [(0.4, 0.3), (0.8, 0.0), (1.2, -0.3)] {
+            for x in -3..=3 {
+                slots.push(Transform::from_xyz(x as f32 * 0.4, y, z));
+            }
+        }
+        slots
+    }
+    fn path(&self) -> Option<&'static str> {
+        None
+    }
+}
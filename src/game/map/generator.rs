@@ -0,0 +1,461 @@
+use crate::game::checkout::{spawn_checkout, CHECKOUT_LANE_OFFSET};
+use crate::game::game::GameSeed;
+use crate::game::map::bakery_rack::BakeryRack;
+use crate::game::map::can_pyramid::CanPyramid;
+use crate::game::map::chest_freezer::ChestFreezer;
+use crate::game::map::dairy_cooler::DairyCooler;
+use crate::game::map::end_cap::EndCap;
+use crate::game::map::misc_shelf::MiscShelf;
+use crate::game::map::produce_bin::ProduceBin;
+use crate::game::map::wall::{
+    spawn_wall_path, OpeningKind, WallOpening, WallPath, WALL_SEGMENT_HEIGHT,
+};
+use crate::game::map::{Category, CategoryDistribution, ShopObject};
+use crate::state::AppState;
+use bevy::app::App;
+use bevy::math::{Affine3A, EulerRot, Mat2, UVec2, Vec2, Vec3, Vec3Swizzles};
+use bevy::prelude::{
+    info, warn, AssetServer, Commands, Entity, Name, OnEnter, Plugin, Res, Resource, Transform,
+};
+use bevy_rapier3d::prelude::Collider;
+use rand::prelude::SliceRandom;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+pub struct StoreGeneratorPlugin;
+impl Plugin for StoreGeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Loading), generate_store_layout);
+        app.init_resource::<StoreSize>();
+    }
+}
+
+/// Every fixture is this long along x, so a row of them reads as one aisle
+const FIXTURE_LENGTH: f32 = 3.0;
+/// Distance between rows of fixtures, front to front
+const ROW_SPACING: f32 = 10.0;
+/// Walkway between the outermost fixtures and the side walls
+const SIDE_AISLE: f32 = 3.5;
+/// Open area between the front wall and the first row, for the entrance and checkout
+const FRONT_DEPTH: f32 = 12.0;
+const ENTRANCE_WIDTH: f32 = 3.0;
+const CHECKOUT_X: f32 = 5.0;
+/// Footprint of the checkout counter, matching its player collider
+const CHECKOUT_HALF_EXTENTS: Vec2 = Vec2::new(0.45, 1.45);
+const PROMO_ATTEMPTS: usize = 8;
+
+/// Space the cart needs on every side to get past something
+const CART_CLEARANCE: f32 = 0.6;
+const NAV_CELL_SIZE: f32 = 0.5;
+
+/// How big a store to generate
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct StoreSize {
+    /// Rows of fixtures, each one department
+    pub aisles: u32,
+    /// Fixtures either side of the central cross aisle, per row
+    pub bays: u32,
+}
+impl Default for StoreSize {
+    fn default() -> Self {
+        StoreSize { aisles: 7, bays: 3 }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Fixture {
+    MiscShelf,
+    ChestFreezer,
+    DairyCooler,
+    ProduceBin,
+    BakeryRack,
+    EndCap,
+    CanPyramid,
+}
+impl Fixture {
+    pub fn spawn(&self, commands: &mut Commands, asset_server: &Res<AssetServer>) -> Entity {
+        match self {
+            Fixture::MiscShelf => MiscShelf.spawn(commands, asset_server),
+            Fixture::ChestFreezer => ChestFreezer.spawn(commands, asset_server),
+            Fixture::DairyCooler => DairyCooler.spawn(commands, asset_server),
+            Fixture::ProduceBin => ProduceBin.spawn(commands, asset_server),
+            Fixture::BakeryRack => BakeryRack.spawn(commands, asset_server),
+            Fixture::EndCap => EndCap.spawn(commands, asset_server),
+            Fixture::CanPyramid => CanPyramid.spawn(commands, asset_server),
+        }
+    }
+
+    pub fn path(&self) -> Option<&'static str> {
+        match self {
+            Fixture::MiscShelf => MiscShelf.path(),
+            Fixture::ChestFreezer => ChestFreezer.path(),
+            Fixture::DairyCooler => DairyCooler.path(),
+            Fixture::ProduceBin => ProduceBin.path(),
+            Fixture::BakeryRack => BakeryRack.path(),
+            Fixture::EndCap => EndCap.path(),
+            Fixture::CanPyramid => CanPyramid.path(),
+        }
+    }
+
+    /// Footprint on the floor, matching the fixture's player collider
+    pub fn half_extents(&self) -> Vec2 {
+        match self {
+            Fixture::MiscShelf => Vec2::new(1.5, 0.55),
+            Fixture::CanPyramid => Vec2::new(0.6, 0.6),
+            _ => Vec2::new(1.5, 0.5),
+        }
+    }
+
+    /// Fixture used for most of a department's aisle
+    fn for_department(category: Category) -> Self {
+        match category {
+            Category::Frozen => Fixture::ChestFreezer,
+            Category::Dairy | Category::Meat => Fixture::DairyCooler,
+            Category::Produce => Fixture::ProduceBin,
+            Category::Bakery => Fixture::BakeryRack,
+            _ => Fixture::MiscShelf,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlacedFixture {
+    pub fixture: Fixture,
+    pub transform: Transform,
+    /// Overrides what the fixture stocks, so a shelf in the snack aisle only holds snacks
+    pub department: Option<Category>,
+}
+
+/// A row of fixtures given over to one department
+#[derive(Clone, Copy, Debug)]
+pub struct Aisle {
+    pub department: Category,
+    pub z: f32,
+}
+
+/// A store laid out from a seed and [`StoreSize`]. Generated when a game starts; spawning it
+/// is up to the level.
+#[derive(Resource, Clone, Debug)]
+pub struct StoreLayout {
+    /// Middle of the floor, in xz
+    pub center: Vec2,
+    /// Half the floor size, walls run around its edge
+    pub half_extents: Vec2,
+    pub fixtures: Vec<PlacedFixture>,
+    /// Around the edge of the floor, with a doorway at the entrance
+    pub walls: WallPath,
+    pub aisles: Vec<Aisle>,
+    pub checkout: Transform,
+    /// Where the player comes in, facing into the store
+    pub entrance: Transform,
+}
+
+impl StoreLayout {
+    pub fn generate(seed: u64, size: StoreSize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let aisles = size.aisles.max(1);
+        let bays = size.bays.max(1);
+        let half_width = FIXTURE_LENGTH * bays as f32 + FIXTURE_LENGTH / 2.0 + SIDE_AISLE;
+        let back = -ROW_SPACING * aisles as f32;
+        let center = Vec2::new(0.0, (FRONT_DEPTH + back) / 2.0);
+        let half_extents = Vec2::new(half_width, (FRONT_DEPTH - back) / 2.0);
+
+        // The last run is the front wall, from left to right
+        let walls = WallPath::closed(vec![
+            Vec3::new(half_width, 0.0, FRONT_DEPTH),
+            Vec3::new(half_width, 0.0, back),
+            Vec3::new(-half_width, 0.0, back),
+            Vec3::new(-half_width, 0.0, FRONT_DEPTH),
+        ])
+        .with_opening(WallOpening {
+            run: 3,
+            center: half_width,
+            width: ENTRANCE_WIDTH,
+            kind: OpeningKind::Door,
+        });
+
+        let checkout_side = if rng.random_bool(0.5) { 1.0 } else { -1.0 };
+        let mut layout = StoreLayout {
+            center,
+            half_extents,
+            fixtures: vec![],
+            walls,
+            aisles: vec![],
+            checkout: Transform::from_xyz(
+                checkout_side * CHECKOUT_X - CHECKOUT_LANE_OFFSET.x,
+                0.0,
+                FRONT_DEPTH / 2.0,
+            ),
+            entrance: Transform::from_xyz(0.0, 0.0, FRONT_DEPTH - 3.0),
+        };
+
+        // Each row is one department, cycling through them all in a random order
+        let mut departments = Category::ALL.to_vec();
+        departments.shuffle(&mut rng);
+        for row in 0..aisles {
+            let department = departments[row as usize % departments.len()];
+            let z = -ROW_SPACING * (row as f32 + 0.5);
+            layout.aisles.push(Aisle { department, z });
+            for side in [-1.0, 1.0] {
+                for bay in 1..=bays {
+                    let outermost = bay == bays;
+                    // Occasional gaps give more ways across the store
+                    if !outermost && rng.random_bool(0.1) {
+                        continue;
+                    }
+                    let (fixture, department) = if outermost && rng.random_bool(0.5) {
+                        (Fixture::EndCap, None)
+                    } else {
+                        (Fixture::for_department(department), Some(department))
+                    };
+                    layout.try_place(PlacedFixture {
+                        fixture,
+                        transform: Transform::from_xyz(side * FIXTURE_LENGTH * bay as f32, 0.0, z),
+                        department,
+                    });
+                }
+            }
+        }
+
+        // Promotional displays dotted around the walkways, wherever they don't block the way
+        for _ in 0..PROMO_ATTEMPTS {
+            let x = rng.random_range(-half_width + 1.0..half_width - 1.0);
+            let z = rng.random_range(back + 1.0..FRONT_DEPTH - 1.0);
+            layout.try_place(PlacedFixture {
+                fixture: Fixture::CanPyramid,
+                transform: Transform::from_xyz(x, 0.0, z),
+                department: None,
+            });
+        }
+
+        layout
+    }
+
+    /// Places a fixture unless it would cut something off from the entrance
+    fn try_place(&mut self, placed: PlacedFixture) {
+        self.fixtures.push(placed);
+        if !self.is_reachable() {
+            self.fixtures.pop();
+        }
+    }
+
+    /// Whether the cart can drive from the entrance to the checkout lane and up to every fixture
+    pub fn is_reachable(&self) -> bool {
+        let grid = NavGrid::new(self);
+        let reached = grid.flood_fill(self.entrance.translation.xz());
+        let lane = self.checkout.transform_point(CHECKOUT_LANE_OFFSET).xz();
+        grid.cell(lane).is_some_and(|cell| reached[cell])
+            && self.fixtures.iter().all(|placed| {
+                grid.cells_around(placed)
+                    .into_iter()
+                    .any(|cell| reached[cell])
+            })
+    }
+}
+
+/// Which spots on the floor the cart fits in
+struct NavGrid {
+    min: Vec2,
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+}
+
+impl NavGrid {
+    fn new(layout: &StoreLayout) -> Self {
+        let min = layout.center - layout.half_extents;
+        let width = (2.0 * layout.half_extents.x / NAV_CELL_SIZE).ceil() as usize;
+        let height = (2.0 * layout.half_extents.y / NAV_CELL_SIZE).ceil() as usize;
+        let mut grid = NavGrid {
+            min,
+            width,
+            height,
+            blocked: vec![false; width * height],
+        };
+        for index in 0..grid.blocked.len() {
+            let from_center = (grid.center_of(index) - layout.center).abs();
+            grid.blocked[index] = (from_center + CART_CLEARANCE)
+                .cmpgt(layout.half_extents)
+                .any();
+        }
+        let footprints = layout
+            .fixtures
+            .iter()
+            .map(|placed| Footprint::of(placed, CART_CLEARANCE))
+            .chain([Footprint::new(
+                layout.checkout,
+                CHECKOUT_HALF_EXTENTS + CART_CLEARANCE,
+            )]);
+        for footprint in footprints {
+            for index in grid.cells_near(&footprint).collect::<Vec<_>>() {
+                if footprint.contains(grid.center_of(index)) {
+                    grid.blocked[index] = true;
+                }
+            }
+        }
+        grid
+    }
+
+    fn center_of(&self, index: usize) -> Vec2 {
+        let (x, y) = (index % self.width, index / self.width);
+        self.min + (Vec2::new(x as f32, y as f32) + 0.5) * NAV_CELL_SIZE
+    }
+
+    fn cell(&self, point: Vec2) -> Option<usize> {
+        let local = ((point - self.min) / NAV_CELL_SIZE).floor();
+        if local.x < 0.0 || local.y < 0.0 {
+            return None;
+        }
+        let (x, y) = (local.x as usize, local.y as usize);
+        (x < self.width && y < self.height).then_some(y * self.width + x)
+    }
+
+    /// Cells overlapping a footprint's bounds, so only those need testing against it
+    fn cells_near(&self, footprint: &Footprint) -> impl Iterator<Item = usize> + '_ {
+        let rotation = Mat2::from_angle(-footprint.transform.rotation.to_euler(EulerRot::YXZ).0);
+        let reach = rotation.abs() * footprint.half_extents;
+        let center = footprint.transform.translation.xz();
+        let to_cell = |point: Vec2| {
+            ((point - self.min) / NAV_CELL_SIZE)
+                .floor()
+                .max(Vec2::ZERO)
+                .as_uvec2()
+        };
+        let (from, to) = (to_cell(center - reach), to_cell(center + reach));
+        let to = to.min(UVec2::new(self.width as u32 - 1, self.height as u32 - 1));
+        (from.y..=to.y)
+            .flat_map(move |y| (from.x..=to.x).map(move |x| y as usize * self.width + x as usize))
+    }
+
+    fn flood_fill(&self, start: Vec2) -> Vec<bool> {
+        let mut reached = vec![false; self.blocked.len()];
+        let Some(start) = self.cell(start).filter(|cell| !self.blocked[*cell]) else {
+            return reached;
+        };
+        reached[start] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some(cell) = queue.pop_front() {
+            let (x, y) = (cell % self.width, cell / self.width);
+            let neighbours = [
+                (x > 0).then(|| cell - 1),
+                (x + 1 < self.width).then(|| cell + 1),
+                (y > 0).then(|| cell - self.width),
+                (y + 1 < self.height).then(|| cell + self.width),
+            ];
+            for next in neighbours.into_iter().flatten() {
+                if !self.blocked[next] && !reached[next] {
+                    reached[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Cells in a ring just outside where the cart is kept from a fixture
+    fn cells_around(&self, placed: &PlacedFixture) -> Vec<usize> {
+        let inner = Footprint::of(placed, CART_CLEARANCE);
+        let outer = Footprint::of(placed, CART_CLEARANCE + NAV_CELL_SIZE);
+        self.cells_near(&outer)
+            .filter(|index| {
+                let point = self.center_of(*index);
+                outer.contains(point) && !inner.contains(point)
+            })
+            .collect()
+    }
+}
+
+struct Footprint {
+    transform: Transform,
+    to_local: Affine3A,
+    half_extents: Vec2,
+}
+
+impl Footprint {
+    fn new(transform: Transform, half_extents: Vec2) -> Self {
+        Footprint {
+            transform,
+            to_local: transform.compute_affine().inverse(),
+            half_extents,
+        }
+    }
+
+    fn of(placed: &PlacedFixture, margin: f32) -> Self {
+        Footprint::new(placed.transform, placed.fixture.half_extents() + margin)
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        let local = self
+            .to_local
+            .transform_point3(Vec3::new(point.x, 0.0, point.y))
+            .xz();
+        local.abs().cmple(self.half_extents).all()
+    }
+}
+
+/// Runs before anything is loaded, the layout decides which models a level needs
+pub fn generate_store_layout(mut commands: Commands, seed: Res<GameSeed>, size: Res<StoreSize>) {
+    let layout = StoreLayout::generate(seed.0, *size);
+    info!(
+        "generated store {:?} with {} fixtures",
+        size.as_ref(),
+        layout.fixtures.len()
+    );
+    commands.insert_resource(layout);
+}
+
+/// Fixtures, walls and checkout of a generated store. The floor and lighting are left to the
+/// level.
+pub fn spawn_store(commands: &mut Commands, asset_server: &Res<AssetServer>, layout: &StoreLayout) {
+    for placed in layout.fixtures.iter() {
+        let fixture = placed.fixture.spawn(commands, asset_server);
+        let mut fixture_ec = commands.entity(fixture);
+        fixture_ec.insert(placed.transform);
+        if let Some(department) = placed.department {
+            fixture_ec.insert(CategoryDistribution(vec![(1.0, department)]));
+        }
+    }
+    if let Err(error) = spawn_wall_path(commands, asset_server, &layout.walls) {
+        warn!("failed to create the store's walls: {error}");
+    }
+    // The entrance is the only doorway and nothing is generated outside it, so an invisible
+    // barrier keeps the cart in the store
+    for (start, end) in layout.walls.doorways() {
+        let half_width = start.distance(end) / 2.0;
+        commands.spawn((
+            Name::new("Entrance Barrier"),
+            Collider::cuboid(half_width, WALL_SEGMENT_HEIGHT / 2.0, 0.05),
+            Transform::from_translation((start + end) / 2.0 + Vec3::Y * WALL_SEGMENT_HEIGHT / 2.0)
+                .looking_to(Vec3::Y.cross(end - start), Vec3::Y),
+        ));
+    }
+    spawn_checkout(commands, asset_server, layout.checkout);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_store_is_reachable_and_repeatable() {
+        for seed in [1, 42] {
+            let layout = StoreLayout::generate(seed, StoreSize::default());
+            assert!(layout.is_reachable(), "seed {seed} cut something off");
+            assert!(
+                layout.fixtures.len() > 20,
+                "seed {seed} left the store bare"
+            );
+
+            let again = StoreLayout::generate(seed, StoreSize::default());
+            let translations = |layout: &StoreLayout| -> Vec<Vec3> {
+                layout
+                    .fixtures
+                    .iter()
+                    .map(|placed| placed.transform.translation)
+                    .collect()
+            };
+            assert_eq!(translations(&layout), translations(&again));
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::game::cart::CartLoad;
+use crate::game::game::{GameMode, ScoreResource};
+use crate::game::player::{CartCollider, Player};
+use crate::hierarchy::get_root_parent_entity;
+use crate::state::{AppState, InGameState};
+use bevy::app::App;
+use bevy::hierarchy::Parent;
+use bevy::math::Vec3;
+use bevy::prelude::{
+    in_state, Commands, Component, Entity, Event, EventReader, EventWriter, IntoSystemConfigs,
+    OnEnter, Plugin, Quat, Query, Reflect, ReflectResource, Res, ResMut, Resource, Time, Transform,
+    Update, With, Without,
+};
+use bevy_egui::EguiPlugin;
+use bevy_inspector_egui::prelude::ReflectInspectorOptions;
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+use bevy_inspector_egui::InspectorOptions;
+use bevy_rapier3d::pipeline::CollisionEvent;
+use bevy_rapier3d::prelude::{RigidBody, Velocity};
+
+pub struct KnockOverPlugin;
+impl Plugin for KnockOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                detect_cart_hitting_shop_objects,
+                wobble_shop_objects,
+                score_mess,
+            )
+                .chain()
+                .run_if(in_state(InGameState::Playing)),
+        );
+        app.add_systems(OnEnter(AppState::InGame), reset_mess);
+        app.add_event::<MessMade>();
+        app.insert_resource(KnockOverResource {
+            wobble_speed: 2.0,
+            knock_over_speed: 7.0,
+            wobble_angle: 0.05,
+            wobble_frequency: 18.0,
+            wobble_damping: 4.0,
+            knock_over_spin: 1.5,
+            knock_over_push: 0.3,
+            mess_penalty: 5,
+            mess_bonus: 10,
+        });
+        app.init_resource::<MessResource>();
+        app.register_type::<KnockOverResource>();
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(ResourceInspectorPlugin::<KnockOverResource>::default());
+        }
+    }
+}
+
+#[derive(Resource, InspectorOptions, Reflect)]
+#[reflect(Resource, InspectorOptions)]
+pub struct KnockOverResource {
+    /// Cart speed at impact that makes a shop object wobble
+    wobble_speed: f32,
+    /// Cart speed at impact that topples a shop object
+    knock_over_speed: f32,
+    /// Peak tilt in radians of a wobble at `knock_over_speed`
+    wobble_angle: f32,
+    wobble_frequency: f32,
+    wobble_damping: f32,
+    knock_over_spin: f32,
+    /// Fraction of the cart's velocity a toppled shop object is sent off with
+    knock_over_push: f32,
+    mess_penalty: i32,
+    mess_bonus: i32,
+}
+
+/// Shop objects that wobble when hit and topple when hit hard enough
+#[derive(Component)]
+pub struct Knockable;
+
+/// A shop object the cart has knocked over, now a dynamic body
+#[derive(Component)]
+pub struct KnockedOver;
+
+#[derive(Component)]
+struct Wobble {
+    rest: Quat,
+    axis: Vec3,
+    angle: f32,
+    elapsed: f32,
+}
+
+/// Sent when a shop object is knocked over
+#[derive(Event)]
+pub struct MessMade {
+    pub shop_object: Entity,
+}
+
+/// Mess made this run, shown in the HUD
+#[derive(Resource, Default)]
+pub struct MessResource {
+    pub knocked_over: u32,
+}
+
+fn detect_cart_hitting_shop_objects(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    parent_q: Query<&Parent>,
+    cart_collider_q: Query<(), With<CartCollider>>,
+    player_q: Query<&CartLoad, With<Player>>,
+    mut knockable_q: Query<(&Transform, Option<&mut Wobble>), With<Knockable>>,
+    settings: Res<KnockOverResource>,
+    mut mess_events: EventWriter<MessMade>,
+) {
+    let Ok(load) = player_q.get_single() else {
+        return;
+    };
+    // The contact has already slowed the cart, so go by how fast it was going into the step
+    let cart_velocity = load.last_linvel.with_y(0.0);
+    let speed = cart_velocity.length();
+    // Several parts of the cart can hit the same object in one step
+    let mut knocked_over = vec![];
+    for event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2, _) = event else {
+            continue;
+        };
+        let other = if cart_collider_q.contains(*e1) {
+            *e2
+        } else if cart_collider_q.contains(*e2) {
+            *e1
+        } else {
+            continue;
+        };
+        let shop_object = get_root_parent_entity(other, &parent_q);
+        let Ok((shop_object_t, wobble)) = knockable_q.get_mut(shop_object) else {
+            continue;
+        };
+        if speed < settings.wobble_speed || knocked_over.contains(&shop_object) {
+            continue;
+        }
+        // Tip away from the cart
+        let axis = Vec3::Y.cross(cart_velocity).normalize_or_zero();
+        if speed >= settings.knock_over_speed {
+            let rest = wobble.map_or(shop_object_t.rotation, |wobble| wobble.rest);
+            commands
+                .entity(shop_object)
+                .remove::<(Knockable, Wobble)>()
+                .insert((
+                    Transform {
+                        rotation: rest,
+                        ..*shop_object_t
+                    },
+                    RigidBody::Dynamic,
+                    Velocity {
+                        linvel: cart_velocity * settings.knock_over_push,
+                        angvel: axis * settings.knock_over_spin,
+                    },
+                    KnockedOver,
+                ));
+            knocked_over.push(shop_object);
+            mess_events.send(MessMade { shop_object });
+        } else {
+            let angle = settings.wobble_angle * speed / settings.knock_over_speed;
+            match wobble {
+                Some(mut wobble) => {
+                    wobble.axis = axis;
+                    wobble.angle = angle;
+                    wobble.elapsed = 0.0;
+                }
+                None => {
+                    commands.entity(shop_object).insert(Wobble {
+                        rest: shop_object_t.rotation,
+                        axis,
+                        angle,
+                        elapsed: 0.0,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn wobble_shop_objects(
+    mut commands: Commands,
+    mut wobble_q: Query<(Entity, &mut Transform, &mut Wobble), Without<KnockedOver>>,
+    settings: Res<KnockOverResource>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut wobble) in wobble_q.iter_mut() {
+        wobble.elapsed += time.delta_secs();
+        let decay = (-settings.wobble_damping * wobble.elapsed).exp();
+        if decay < 0.01 {
+            transform.rotation = wobble.rest;
+            commands.entity(entity).remove::<Wobble>();
+            continue;
+        }
+        let tilt = wobble.angle * decay * (settings.wobble_frequency * wobble.elapsed).sin();
+        transform.rotation = Quat::from_axis_angle(wobble.axis, tilt) * wobble.rest;
+    }
+}
+
+fn reset_mess(mut mess: ResMut<MessResource>) {
+    *mess = MessResource::default();
+}
+
+fn score_mess(
+    mut mess_events: EventReader<MessMade>,
+    mut mess: ResMut<MessResource>,
+    mut score_res: ResMut<ScoreResource>,
+    game_mode: Res<GameMode>,
+    settings: Res<KnockOverResource>,
+) {
+    for _ in mess_events.read() {
+        mess.knocked_over += 1;
+        score_res.score += match *game_mode {
+            GameMode::Shopping => -settings.mess_penalty,
+            GameMode::Rampage => settings.mess_bonus,
+        };
+    }
+}
@@ -0,0 +1,297 @@
+use crate::game::map::generator::{Fixture, StoreLayout};
+use crate::quality::QualityPreset;
+use bevy::app::App;
+use bevy::color::Color;
+use bevy::math::{Vec2, Vec3};
+use bevy::pbr::{CascadeShadowConfig, CascadeShadowConfigBuilder};
+use bevy::prelude::{
+    default, AmbientLight, Commands, Component, DirectionalLight, Name, Plugin, PointLight, Quat,
+    Resource, Transform,
+};
+use std::f32::consts::PI;
+
+pub struct LightingPlugin;
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightBudget>();
+    }
+}
+
+const CEILING_LIGHT_HEIGHT: f32 = 2.8;
+const CEILING_LIGHT_SPACING: f32 = 10.0;
+const ZONE_LIGHT_HEIGHT: f32 = 2.5;
+const AMBIENT_BRIGHTNESS: f32 = 80.0;
+/// Ambient brightness added back when every planned light is over budget
+const AMBIENT_FILL: f32 = 400.0;
+
+/// How many lights a store may spawn. Point lights past `max_point_lights` are dropped, least
+/// important first, and only the first `max_shadow_casters` ceiling lights cast shadows.
+/// Set from the [`QualityPreset`].
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LightBudget {
+    pub max_point_lights: usize,
+    pub max_shadow_casters: usize,
+    pub directional_shadows: bool,
+    pub shadow_cascades: usize,
+    /// How far from the camera the sun's shadows reach
+    pub shadow_distance: f32,
+}
+impl Default for LightBudget {
+    fn default() -> Self {
+        QualityPreset::default().light_budget()
+    }
+}
+impl LightBudget {
+    pub fn cascade_shadow_config(&self) -> CascadeShadowConfig {
+        CascadeShadowConfigBuilder {
+            num_cascades: self.shadow_cascades.max(1),
+            maximum_distance: self.shadow_distance,
+            ..default()
+        }
+        .build()
+    }
+}
+
+/// Most important first, which is the order lights are kept in when over budget
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum LightKind {
+    Ceiling,
+    /// Tinted light over an aisle in its department's color
+    Zone,
+    /// Cold glow from freezers and coolers
+    FridgeGlow,
+}
+
+impl LightKind {
+    /// Lights of this kind kept out of `max_point_lights` before spare ones of any other kind
+    fn reserved(&self, max_point_lights: usize) -> usize {
+        match self {
+            LightKind::Ceiling => max_point_lights / 2,
+            LightKind::Zone | LightKind::FridgeGlow => max_point_lights / 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlannedLight {
+    pub kind: LightKind,
+    pub position: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    pub range: f32,
+    pub shadows: bool,
+}
+
+/// Every light in a store, already cut down to a [`LightBudget`]
+#[derive(Clone, Debug)]
+pub struct LightingPlan {
+    pub lights: Vec<PlannedLight>,
+    /// Makes up for lights dropped by the budget
+    pub ambient_brightness: f32,
+    pub directional_shadows: bool,
+    pub cascade_shadow_config: CascadeShadowConfig,
+}
+
+/// Marks lights spawned from a [`LightingPlan`]
+#[derive(Component)]
+pub struct StoreLight;
+
+impl LightingPlan {
+    pub fn for_layout(layout: &StoreLayout, budget: &LightBudget) -> Self {
+        let mut lights = vec![];
+        let min = layout.center - layout.half_extents;
+        // Spread out to fit the ceiling's share of the budget, reaching further to make up
+        let full_grid = ceiling_grid(layout.half_extents, CEILING_LIGHT_SPACING);
+        let mut spacing = CEILING_LIGHT_SPACING;
+        let (columns, rows) = loop {
+            let (columns, rows) = ceiling_grid(layout.half_extents, spacing);
+            if columns * rows <= LightKind::Ceiling.reserved(budget.max_point_lights).max(1) {
+                break (columns, rows);
+            }
+            spacing *= 1.25;
+        };
+        let cell = 2.0 * layout.half_extents / Vec2::new(columns as f32, rows as f32);
+        for column in 0..columns {
+            for row in 0..rows {
+                let xz = min + cell * Vec2::new(column as f32 + 0.5, row as f32 + 0.5);
+                lights.push(PlannedLight {
+                    kind: LightKind::Ceiling,
+                    position: Vec3::new(xz.x, CEILING_LIGHT_HEIGHT, xz.y),
+                    color: Color::srgb(1.0, 0.97, 0.9),
+                    intensity: 20_000.0 * (spacing / CEILING_LIGHT_SPACING).powi(2),
+                    range: spacing * 1.5,
+                    shadows: false,
+                });
+            }
+        }
+        // Shadows where the player spends the most time
+        let entrance = layout.entrance.translation;
+        lights.sort_by(|a, b| {
+            a.position
+                .distance_squared(entrance)
+                .total_cmp(&b.position.distance_squared(entrance))
+        });
+
+        for aisle in layout.aisles.iter() {
+            for side in [-1.0, 1.0] {
+                lights.push(PlannedLight {
+                    kind: LightKind::Zone,
+                    position: Vec3::new(
+                        side * layout.half_extents.x / 2.0,
+                        ZONE_LIGHT_HEIGHT,
+                        aisle.z,
+                    ),
+                    color: aisle.department.zone_color(),
+                    intensity: 8_000.0,
+                    range: layout.half_extents.x,
+                    shadows: false,
+                });
+            }
+        }
+
+        for placed in layout.fixtures.iter() {
+            let glow_height = match placed.fixture {
+                Fixture::ChestFreezer => 0.8,
+                Fixture::DairyCooler => 1.2,
+                _ => continue,
+            };
+            lights.push(PlannedLight {
+                kind: LightKind::FridgeGlow,
+                position: placed.transform.translation + Vec3::Y * glow_height,
+                color: Color::srgb(0.6, 0.85, 1.0),
+                intensity: 2_000.0,
+                range: 3.0,
+                shadows: false,
+            });
+        }
+
+        // Counting the ceiling lights left out by spreading them
+        let planned = lights.len() + full_grid.0 * full_grid.1 - columns * rows;
+        // Stable, so ceiling lights keep their order from the entrance
+        lights.sort_by_key(|light| light.kind);
+        // Every kind keeps its share of the budget, then the rest goes most important first
+        let mut kept: Vec<PlannedLight> = vec![];
+        let mut spare = vec![];
+        for light in lights {
+            let of_kind = kept.iter().filter(|kept| kept.kind == light.kind).count();
+            if of_kind < light.kind.reserved(budget.max_point_lights) {
+                kept.push(light);
+            } else {
+                spare.push(light);
+            }
+        }
+        let room = budget.max_point_lights.saturating_sub(kept.len());
+        kept.extend(spare.into_iter().take(room));
+        kept.sort_by_key(|light| light.kind);
+        let mut lights = kept;
+        for light in lights
+            .iter_mut()
+            .filter(|light| light.kind == LightKind::Ceiling)
+            .take(budget.max_shadow_casters)
+        {
+            light.shadows = true;
+        }
+        let dropped = (planned - lights.len()) as f32 / planned.max(1) as f32;
+        LightingPlan {
+            lights,
+            ambient_brightness: AMBIENT_BRIGHTNESS + AMBIENT_FILL * dropped,
+            directional_shadows: budget.directional_shadows,
+            cascade_shadow_config: budget.cascade_shadow_config(),
+        }
+    }
+}
+
+/// Columns and rows of ceiling lights about `spacing` apart
+fn ceiling_grid(half_extents: Vec2, spacing: f32) -> (usize, usize) {
+    let cells = (2.0 * half_extents / spacing).ceil().max(Vec2::ONE);
+    (cells.x as usize, cells.y as usize)
+}
+
+pub fn spawn_lighting(commands: &mut Commands, plan: &LightingPlan) {
+    for light in plan.lights.iter() {
+        commands.spawn((
+            Name::new(format!("{:?} Light", light.kind)),
+            PointLight {
+                color: light.color,
+                intensity: light.intensity,
+                range: light.range,
+                shadows_enabled: light.shadows,
+                ..default()
+            },
+            Transform::from_translation(light.position),
+            StoreLight,
+        ));
+    }
+    commands.spawn((
+        Name::new("Sun"),
+        DirectionalLight {
+            illuminance: 2_000.0,
+            shadows_enabled: plan.directional_shadows,
+            ..default()
+        },
+        plan.cascade_shadow_config.clone(),
+        Transform {
+            translation: Vec3::new(0.0, 10.0, 0.0),
+            rotation: Quat::from_rotation_x(-PI / 4.),
+            ..default()
+        },
+        StoreLight,
+    ));
+    commands.insert_resource(AmbientLight {
+        brightness: plan.ambient_brightness,
+        ..default()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::map::generator::StoreSize;
+
+    #[test]
+    fn lighting_plan_keeps_to_the_light_budget() {
+        let layout = StoreLayout::generate(7, StoreSize::default());
+        let roomy = LightingPlan::for_layout(
+            &layout,
+            &LightBudget {
+                max_point_lights: usize::MAX,
+                max_shadow_casters: 4,
+                ..LightBudget::default()
+            },
+        );
+        assert!(roomy
+            .lights
+            .iter()
+            .any(|light| light.kind == LightKind::FridgeGlow));
+
+        let budget = LightBudget {
+            max_point_lights: 10,
+            max_shadow_casters: 2,
+            ..LightBudget::default()
+        };
+        let plan = LightingPlan::for_layout(&layout, &budget);
+        assert_eq!(plan.lights.len(), 10);
+        assert_eq!(plan.lights.iter().filter(|light| light.shadows).count(), 2);
+        assert!(
+            plan.lights
+                .windows(2)
+                .all(|pair| pair[0].kind <= pair[1].kind),
+            "lights should be kept most important first"
+        );
+        assert!(plan.ambient_brightness > roomy.ambient_brightness);
+    }
+
+    #[test]
+    fn default_store_keeps_every_kind_of_light_on_medium() {
+        let layout = StoreLayout::generate(7, StoreSize::default());
+        let budget = QualityPreset::Medium.light_budget();
+        let plan = LightingPlan::for_layout(&layout, &budget);
+        assert!(plan.lights.len() <= budget.max_point_lights);
+        for kind in [LightKind::Ceiling, LightKind::Zone, LightKind::FridgeGlow] {
+            assert!(
+                plan.lights.iter().any(|light| light.kind == kind),
+                "no {kind:?} lights were kept"
+            );
+        }
+    }
+}
@@ -1,16 +1,10 @@
-use crate::game::item::{ItemPickup, ItemPickupCollider};
-use crate::game::map::{Category, ShopObject, ShopObjectScene};
-use bevy::asset::AssetServer;
-use bevy::core::Name;
-use bevy::hierarchy::{BuildChildren, ChildBuild};
-use bevy::prelude::{
-    Bundle, Children, Commands, Entity, HierarchyQueryExt, Query, Res, SceneRoot, Transform,
-    Trigger, With, Without,
-};
-use bevy::scene::SceneInstanceReady;
+use crate::game::map::{Category, ShopObject};
+use crate::game::scene_collider::{SceneColliderShape, SceneColliders};
+use bevy::prelude::{Bundle, Transform};
 use bevy_rapier3d::geometry::Group;
 use bevy_rapier3d::prelude::{Collider, CollisionGroups};
 
+/// Double sided shelving unit, stocked from both faces
 pub struct MiscShelf;
 
 impl ShopObject for MiscShelf {
@@ -18,37 +12,10 @@ impl ShopObject for MiscShelf {
         vec![(0.5, Category::Snacks), (0.5, Category::Condiments)]
     }
 
-    fn colliders_with_transforms(&self) -> Vec<impl Bundle> {
-        vec![
-            (
-                Collider::cuboid(0.035, 0.94, 0.0475),
-                Transform::from_xyz(-1.43, 0.94, 0.395),
-            ),
-            (
-                Collider::cuboid(0.035, 0.94, 0.0475),
-                Transform::from_xyz(-1.43, 0.94, -0.395),
-            ),
-            (
-                Collider::cuboid(0.035, 0.94, 0.0475),
-                Transform::from_xyz(1.43, 0.94, 0.395),
-            ),
-            (
-                Collider::cuboid(0.035, 0.94, 0.0475),
-                Transform::from_xyz(1.43, 0.94, -0.395),
-            ),
-            (
-                Collider::cuboid(1.4, 0.025, 0.45),
-                Transform::from_xyz(0.0, 0.375, 0.0),
-            ),
-            (
-                Collider::cuboid(1.4, 0.025, 0.45),
-                Transform::from_xyz(0.0, 1.075, 0.0),
-            ),
-            (
-                Collider::cuboid(1.4, 0.025, 0.45),
-                Transform::from_xyz(0.0, 1.74, 0.0),
-            ),
-        ]
+    fn scene_colliders(&self) -> Option<SceneColliders> {
+        Some(SceneColliders {
+            fallback: SceneColliderShape::PartAabbs,
+        })
     }
     fn player_collider(&self) -> impl Bundle {
         (
@@ -57,7 +24,7 @@ impl ShopObject for MiscShelf {
             CollisionGroups::new(Group::GROUP_4, Group::GROUP_1),
         )
     }
-    fn path(&self) -> &str {
-        "models/SM_Prop_Shop_Shelf_Basic_01.glb#Scene0"
+    fn path(&self) -> Option<&'static str> {
+        Some("models/SM_Prop_Shop_Shelf_Basic_01.glb#Scene0")
     }
 }
@@ -1,31 +1,47 @@
-use crate::game::item::{ItemPickup, ItemPickupCollider};
+use crate::game::map::generator::StoreGeneratorPlugin;
+use crate::game::map::knock_over::{KnockOverPlugin, Knockable};
+use crate::game::map::lighting::LightingPlugin;
+use crate::game::scene_collider::SceneColliders;
+use crate::game::stock::StockSlots;
 use bevy::app::App;
 use bevy::asset::AssetServer;
+use bevy::color::Color;
 use bevy::core::Name;
 use bevy::hierarchy::{BuildChildren, ChildBuild, Children, HierarchyQueryExt};
 use bevy::prelude::{
-    Bundle, Commands, Component, Entity, OnAdd, Plugin, Query, Res, SceneRoot, Transform, Trigger,
-    With, Without,
+    default, Bundle, Commands, Component, Cuboid, Entity, Mesh, Mesh3d, MeshMaterial3d, OnAdd,
+    Plugin, Query, Res, SceneRoot, StandardMaterial, Transform, Trigger, Visibility, With, Without,
 };
 use bevy::scene::SceneInstanceReady;
 use bevy_rapier3d::geometry::Collider;
-use rand::distr::weighted::WeightedIndex;
-use rand::prelude::Distribution;
 
+pub mod bakery_rack;
+pub mod can_pyramid;
+pub mod checkout;
+pub mod chest_freezer;
+pub mod dairy_cooler;
+pub mod end_cap;
+pub mod generator;
+pub mod knock_over;
+pub mod lighting;
 pub mod misc_shelf;
+pub mod produce_bin;
 pub mod wall;
 
 pub struct MapPlugin;
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
         app.add_observer(on_spawned_shop_object_observe_scene_ready);
+        app.add_plugins(KnockOverPlugin);
+        app.add_plugins(StoreGeneratorPlugin);
+        app.add_plugins(LightingPlugin);
     }
 }
 
 #[derive(Component)]
 pub struct ShopObjectScene;
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum Category {
     Bakery,
     Produce,
@@ -38,30 +54,112 @@ pub enum Category {
     Condiments,
 }
 
+impl Category {
+    pub const ALL: [Category; 9] = [
+        Category::Bakery,
+        Category::Produce,
+        Category::Dairy,
+        Category::Meat,
+        Category::Canned,
+        Category::Snacks,
+        Category::Beverages,
+        Category::Frozen,
+        Category::Condiments,
+    ];
+
+    pub fn item_path(&self) -> &'static str {
+        // Until each category has its own product models
+        match self {
+            Category::Condiments => "models/syrup.glb#Scene0",
+            _ => "models/burger.glb#Scene0",
+        }
+    }
+
+    /// Tint of the lighting over this department's aisle
+    pub fn zone_color(&self) -> Color {
+        match self {
+            Category::Bakery => Color::srgb(1.0, 0.8, 0.55),
+            Category::Produce => Color::srgb(0.75, 1.0, 0.6),
+            Category::Dairy | Category::Frozen => Color::srgb(0.7, 0.85, 1.0),
+            Category::Meat => Color::srgb(1.0, 0.7, 0.7),
+            Category::Canned | Category::Condiments => Color::srgb(1.0, 0.95, 0.75),
+            Category::Snacks => Color::srgb(1.0, 0.85, 0.6),
+            Category::Beverages => Color::srgb(0.75, 0.9, 1.0),
+        }
+    }
+}
+
+/// Colour of the boxes drawn for shop objects that haven't been modelled yet
+const STAND_IN_COLOR: Color = Color::srgb(0.6, 0.62, 0.65);
+
 #[derive(Component)]
 pub struct CategoryDistribution(pub Vec<(f32, Category)>);
 
+/// Category of a stocked item, for stock stats
+#[derive(Component, Clone, Copy)]
+pub struct ItemCategory(pub Category);
+
 pub trait ShopObject {
     fn spawn(&self, commands: &mut Commands, asset_server: &Res<AssetServer>) -> Entity {
-        let mut obj = commands.spawn(self.scene(&asset_server));
+        let mut obj = match self.path() {
+            Some(path) => commands.spawn(SceneRoot(asset_server.load(path))),
+            None => commands.spawn((Transform::default(), Visibility::default())),
+        };
         obj.insert(ShopObjectScene);
         obj.insert(CategoryDistribution(self.categories()));
+        obj.insert(StockSlots::new(
+            self.item_slots().iter().map(|t| t.translation),
+        ));
+        if let Some(scene_colliders) = self.scene_colliders() {
+            obj.insert(scene_colliders);
+        }
+        if self.knockable() {
+            obj.insert(Knockable);
+        }
+        let stand_in = self.path().is_none().then(|| {
+            asset_server.add(StandardMaterial {
+                base_color: STAND_IN_COLOR,
+                perceptual_roughness: 0.8,
+                ..default()
+            })
+        });
         obj.with_children(|parent| {
-            for collider_with_transform in self.colliders_with_transforms() {
-                parent.spawn(collider_with_transform);
+            for (collider, transform) in self.colliders_with_transforms() {
+                let mut collider_ec = parent.spawn(transform);
+                // Drawn as the boxes it's made of until it has a model
+                if let (Some(material), Some(cuboid)) = (&stand_in, collider.as_cuboid()) {
+                    collider_ec.insert((
+                        Mesh3d(
+                            asset_server
+                                .add(Mesh::from(Cuboid::from_size(2.0 * cuboid.half_extents()))),
+                        ),
+                        MeshMaterial3d(material.clone()),
+                    ));
+                }
+                collider_ec.insert(collider);
             }
             parent.spawn(self.player_collider());
         });
         obj.id()
     }
-    fn scene(&self, asset_server: &Res<AssetServer>) -> SceneRoot {
-        let scene = asset_server.load(self.path());
-        SceneRoot(scene)
-    }
     fn categories(&self) -> Vec<(f32, Category)>;
-    fn colliders_with_transforms(&self) -> Vec<impl Bundle>;
+    /// Hand placed colliders, for models without any to generate them from
+    fn colliders_with_transforms(&self) -> Vec<(Collider, Transform)> {
+        vec![]
+    }
+    fn scene_colliders(&self) -> Option<SceneColliders> {
+        None
+    }
     fn player_collider(&self) -> impl Bundle;
-    fn path(&self) -> &str;
+    /// Item positions stocked besides the `Item*` nodes in its scene
+    fn item_slots(&self) -> Vec<Transform> {
+        vec![]
+    }
+    fn knockable(&self) -> bool {
+        true
+    }
+    /// Scene to show, or `None` while it hasn't been modelled and its colliders are drawn instead
+    fn path(&self) -> Option<&'static str>;
 }
 
 fn on_spawned_shop_object_observe_scene_ready(
@@ -75,38 +173,20 @@ fn on_spawned_shop_object_observe_scene_ready(
 
 fn on_shop_object_finish(
     trigger: Trigger<SceneInstanceReady>,
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
     child_q: Query<&Children>,
     name_t_q: Query<(&Name, &Transform), Without<SceneRoot>>,
-    t_q: Query<(&Transform, &CategoryDistribution), With<SceneRoot>>,
+    mut slots_q: Query<&mut StockSlots, With<SceneRoot>>,
 ) {
-    let burger = asset_server.load("models/burger.glb#Scene0");
-    let (parent_t, category_dist) = t_q.get(trigger.entity()).unwrap();
-    let category_weights: Vec<f32> = category_dist.0.iter().map(|(weight, _)| *weight).collect();
-    let dist = WeightedIndex::new(&category_weights).unwrap();
-    let mut rng = rand::rng();
-    for child in child_q.iter_descendants(trigger.entity()) {
-        if let Ok((name, t)) = name_t_q.get(child) {
-            if name.as_str().starts_with("Item") {
-                let item_category = &category_dist.0[dist.sample(&mut rng)].1;
-                // Fixme: distribute items based on category
-                if *item_category == Category::Snacks {
-                    let mut ec = commands.spawn((
-                        Name::new("Burger"),
-                        SceneRoot(burger.clone()),
-                        Transform::from_translation(t.translation + parent_t.translation),
-                        ItemPickup,
-                    ));
-                    ec.with_children(|parent| {
-                        parent.spawn((
-                            Collider::cuboid(0.1, 0.1, 0.1),
-                            Transform::from_xyz(0.0, 0.1, 0.0),
-                            ItemPickupCollider,
-                        ));
-                    });
-                }
-            }
-        }
-    }
+    let Ok(mut stock_slots) = slots_q.get_mut(trigger.entity()) else {
+        return;
+    };
+    // The stock manager fills these along with the slots given at spawn
+    let named_slots = StockSlots::new(
+        child_q
+            .iter_descendants(trigger.entity())
+            .filter_map(|child| name_t_q.get(child).ok())
+            .filter(|(name, _)| name.as_str().starts_with("Item"))
+            .map(|(_, t)| t.translation),
+    );
+    stock_slots.0.extend(named_slots.0);
 }
@@ -0,0 +1,73 @@
+use crate::game::map::{Category, ShopObject};
+use bevy::prelude::{Bundle, Transform};
+use bevy_rapier3d::geometry::Group;
+use bevy_rapier3d::prelude::{Collider, CollisionGroups};
+
+/// Row of waist high bins with a lip to keep the produce piled on top
+pub struct ProduceBin;
+
+impl ShopObject for ProduceBin {
+    fn categories(&self) -> Vec<(f32, Category)> {
+        vec![(1.0, Category::Produce)]
+    }
+
+    fn colliders_with_transforms(&self) -> Vec<(Collider, Transform)> {
+        vec![
+            (
+                Collider::cuboid(1.45, 0.35, 0.45),
+                Transform::from_xyz(0.0, 0.35, 0.0),
+            ),
+            // Lip
+            (
+                Collider::cuboid(1.5, 0.1, 0.03),
+                Transform::from_xyz(0.0, 0.8, 0.47),
+            ),
+            (
+                Collider::cuboid(1.5, 0.1, 0.03),
+                Transform::from_xyz(0.0, 0.8, -0.47),
+            ),
+            (
+                Collider::cuboid(0.03, 0.1, 0.45),
+                Transform::from_xyz(1.47, 0.8, 0.0),
+            ),
+            (
+                Collider::cuboid(0.03, 0.1, 0.45),
+                Transform::from_xyz(-1.47, 0.8, 0.0),
+            ),
+            // Dividers between the bins
+            (
+                Collider::cuboid(0.02, 0.1, 0.45),
+                Transform::from_xyz(-0.5, 0.8, 0.0),
+            ),
+            (
+                Collider::cuboid(0.02, 0.1, 0.45),
+                Transform::from_xyz(0.5, 0.8, 0.0),
+            ),
+        ]
+    }
+    fn player_collider(&self) -> impl Bundle {
+        (
+            Collider::cuboid(1.5, 0.45, 0.5),
+            Transform::from_xyz(0.0, 0.45, 0.0),
+            CollisionGroups::new(Group::GROUP_4, Group::GROUP_1),
+        )
+    }
+    fn item_slots(&self) -> Vec<Transform> {
+        let mut slots = vec![];
+        for bin in [-1.0, 0.0, 1.0] {
+            for (x, z) in [
+                (-0.2, -0.2),
+                (0.2, -0.2),
+                (-0.2, 0.2),
+                (0.2, 0.2),
+                (0.0, 0.0),
+            ] {
+                slots.push(Transform::from_xyz(bin + x, 0.7, z));
+            }
+        }
+        slots
+    }
+    fn path(&self) -> Option<&'static str> {
+        None
+    }
+}
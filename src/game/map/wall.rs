@@ -1,106 +1,526 @@
-use bevy::asset::ErasedAssetLoader;
 use bevy::math::Vec3;
 use bevy::prelude::{
-    info, AssetServer, BuildChildren, ChildBuild, Commands, Entity, Mesh3d, PointLight, Quat, Res,
+    AssetServer, BuildChildren, ChildBuild, ChildBuilder, Commands, Entity, Name, Quat, Res,
     Transform, Vec2, Vec3Swizzles,
 };
 use bevy::scene::SceneRoot;
 use bevy_rapier3d::prelude::Collider;
-use blenvy::default;
 use std::f32::consts::PI;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
 
 const WALL_SEGMENT_WIDTH: f32 = 2.5;
-const WALL_SEGMENT_HEIGHT: f32 = 3.01;
+pub const WALL_SEGMENT_HEIGHT: f32 = 3.01;
 const WALL_SEGMENT_DEPTH: f32 = 0.225;
 const WALL_LIGHT_MARGIN: f32 = 0.2;
+pub const WALL_SCENE_PATH: &str = "models/SM_Bld_Base_Wall_01.glb#Scene0";
+pub const WALL_LIGHT_SCENE_PATH: &str = "models/SM_Prop_Lighting_Wall_03.glb#Scene0";
 
+/// Walls along a line of points, all at the same height. Each run between two points is
+/// tiled with wall segments, stretched slightly so they end exactly at the next point.
+#[derive(Clone, Debug)]
+pub struct WallPath {
+    pub points: Vec<Vec3>,
+    /// Joins the last point back to the first
+    pub closed: bool,
+    pub openings: Vec<WallOpening>,
+}
+
+impl WallPath {
+    pub fn open(points: Vec<Vec3>) -> Self {
+        WallPath {
+            points,
+            closed: false,
+            openings: vec![],
+        }
+    }
+
+    pub fn closed(points: Vec<Vec3>) -> Self {
+        WallPath {
+            points,
+            closed: true,
+            openings: vec![],
+        }
+    }
+
+    pub fn with_opening(mut self, opening: WallOpening) -> Self {
+        self.openings.push(opening);
+        self
+    }
+
+    /// Lengths of wall left between doorways, e.g. for drawing the walls from above
+    pub fn solid_runs(&self) -> Vec<(Vec3, Vec3)> {
+        let mut solid = vec![];
+        for (index, (start, end)) in self.runs().into_iter().enumerate() {
+            let length = start.xz().distance(end.xz());
+            let mut from = 0.0;
+            for gap in self.door_gaps(index, start, end) {
+                if gap.start > from {
+                    solid.push((
+                        start.lerp(end, from / length),
+                        start.lerp(end, gap.start / length),
+                    ));
+                }
+                from = gap.end.max(from);
+            }
+            if from < length {
+                solid.push((start.lerp(end, from / length), end));
+            }
+        }
+        solid
+    }
+
+    /// Where each doorway really opens, which is wider than asked for since every wall segment
+    /// it touches is left out
+    pub fn doorways(&self) -> Vec<(Vec3, Vec3)> {
+        let mut doorways = vec![];
+        for (index, (start, end)) in self.runs().into_iter().enumerate() {
+            let length = start.xz().distance(end.xz());
+            for gap in self.door_gaps(index, start, end) {
+                doorways.push((
+                    start.lerp(end, gap.start / length),
+                    start.lerp(end, gap.end / length),
+                ));
+            }
+        }
+        doorways
+    }
+
+    /// Distances along a run left open by its doors, in order
+    fn door_gaps(&self, run: usize, start: Vec3, end: Vec3) -> Vec<Range<f32>> {
+        let segment_width = start.xz().distance(end.xz()) / segment_count(start, end) as f32;
+        let mut gaps: Vec<Range<f32>> = self
+            .openings
+            .iter()
+            .filter(|opening| opening.run == run && opening.kind == OpeningKind::Door)
+            .map(|opening| {
+                let covered = segments_covered(start, end, opening);
+                covered.start as f32 * segment_width..covered.end as f32 * segment_width
+            })
+            .collect();
+        gaps.sort_by(|a, b| a.start.total_cmp(&b.start));
+        gaps
+    }
+
+    fn runs(&self) -> Vec<(Vec3, Vec3)> {
+        let mut runs: Vec<(Vec3, Vec3)> = self
+            .points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        if self.closed && self.points.len() > 2 {
+            runs.push((self.points[self.points.len() - 1], self.points[0]));
+        }
+        runs
+    }
+}
+
+/// A gap in one run of a wall. It takes up every wall segment it overlaps.
+#[derive(Clone, Copy, Debug)]
+pub struct WallOpening {
+    /// Index of the run, counting from the first point
+    pub run: usize,
+    /// Distance from the start of the run to the middle of the opening
+    pub center: f32,
+    pub width: f32,
+    pub kind: OpeningKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OpeningKind {
+    /// Open floor to ceiling
+    Door,
+    /// Wall below `sill` and above `sill + height`
+    Window { sill: f32, height: f32 },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WallError {
+    TooFewPoints(usize),
+    /// Every point must be at the height of the first
+    UnevenHeight {
+        point: usize,
+        y: f32,
+        expected: f32,
+    },
+    ZeroLengthRun(usize),
+    OpeningOutOfRange(usize),
+    OverlappingOpenings(usize),
+    /// A window must sit between the floor and the top of the wall
+    WindowDoesNotFit(usize),
+}
+impl Display for WallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WallError::TooFewPoints(count) => {
+                write!(f, "Wall needs at least 2 points, got {count}")
+            }
+            WallError::UnevenHeight { point, y, expected } => write!(
+                f,
+                "Wall points must be at the same y, point {point} is at {y} vs {expected}"
+            ),
+            WallError::ZeroLengthRun(run) => write!(f, "Wall run {run} has no length"),
+            WallError::OpeningOutOfRange(run) => {
+                write!(f, "Wall opening doesn't fit in run {run}")
+            }
+            WallError::OverlappingOpenings(run) => {
+                write!(f, "Wall openings overlap in run {run}")
+            }
+            WallError::WindowDoesNotFit(run) => {
+                write!(f, "Window in run {run} doesn't fit the height of the wall")
+            }
+        }
+    }
+}
+impl std::error::Error for WallError {}
+
+/// A straight wall from `start` to `end`
 pub fn spawn_walls(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     start: Vec3,
     end: Vec3,
-) -> Result<Vec<Entity>, String> {
-    if start.y != end.y {
-        Err(format!(
-            "Wall start & end must be same y, got {} vs {}",
-            start.y, end.y
-        ))
+) -> Result<Vec<Entity>, WallError> {
+    spawn_wall_path(commands, asset_server, &WallPath::open(vec![start, end]))
+}
+
+/// Spawns every run of `path`, with posts where runs meet. Returns the wall segments.
+pub fn spawn_wall_path(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    path: &WallPath,
+) -> Result<Vec<Entity>, WallError> {
+    let runs = validate(path)?;
+    let mut entities = vec![];
+    for (index, (start, end)) in runs.iter().enumerate() {
+        let skipped: Vec<(Range<usize>, OpeningKind)> = path
+            .openings
+            .iter()
+            .filter(|opening| opening.run == index)
+            .map(|opening| (segments_covered(*start, *end, opening), opening.kind))
+            .collect();
+        spawn_wall_run(
+            commands,
+            asset_server,
+            *start,
+            *end,
+            &skipped,
+            &mut entities,
+        );
+    }
+
+    // Each run ends flush with its point, which leaves a wedge open on the outside of a bend
+    let corners = if path.closed {
+        0..path.points.len()
     } else {
-        let start_xz = start.xz();
-        let end_xz = end.xz();
-        let length = (end_xz - start_xz).abs();
-        let direction = (end_xz - start_xz).normalize();
-        let horizontal = Vec3::new(direction.x, 0.0, direction.y).normalize();
-        let angle = horizontal.x.atan2(horizontal.z);
-        let rotation = Quat::from_rotation_y(angle + PI / 2.);
-        let num_segments = (length.length() / WALL_SEGMENT_WIDTH).ceil() as i32;
-        info!("length; {}", length);
-        info!("segments; {}", num_segments);
-        let mut entities = vec![];
+        1..path.points.len() - 1
+    };
+    for corner in corners {
+        // Runs start at the point with the same index
+        let (start, end) = runs[corner % runs.len()];
+        let direction = (end.xz() - start.xz()).normalize();
+        let rotation = Quat::from_rotation_y(direction.x.atan2(direction.y) + PI / 2.);
+        let far_end = direction * WALL_SEGMENT_DEPTH / 2.0;
         commands
-            .spawn(Transform::from_translation(start))
+            .spawn((Name::new("Wall Corner"), Transform::from_translation(start)))
             .with_children(|parent| {
-                for i in 1..=num_segments {
-                    let pos = direction * i as f32 * WALL_SEGMENT_WIDTH;
-                    info!("segment {}; pos: {}", i, pos);
-                    info!("global pos: {}", pos + start_xz);
+                parent.spawn((
+                    SceneRoot(asset_server.load(WALL_SCENE_PATH)),
+                    Transform::from_xyz(far_end.x, 0.0, far_end.y)
+                        .with_rotation(rotation)
+                        .with_scale(Vec3::new(WALL_SEGMENT_DEPTH / WALL_SEGMENT_WIDTH, 1.0, 1.0)),
+                ));
+                parent.spawn((
+                    Collider::cylinder(WALL_SEGMENT_HEIGHT / 2.0, WALL_SEGMENT_DEPTH / 2.0),
+                    Transform::from_xyz(0.0, WALL_SEGMENT_HEIGHT / 2.0, 0.0),
+                ));
+            });
+    }
+    Ok(entities)
+}
+
+fn validate(path: &WallPath) -> Result<Vec<(Vec3, Vec3)>, WallError> {
+    if path.points.len() < 2 {
+        return Err(WallError::TooFewPoints(path.points.len()));
+    }
+    let expected = path.points[0].y;
+    if let Some((point, p)) = path
+        .points
+        .iter()
+        .enumerate()
+        .find(|(_, p)| p.y != expected)
+    {
+        return Err(WallError::UnevenHeight {
+            point,
+            y: p.y,
+            expected,
+        });
+    }
+    let runs = path.runs();
+    if let Some(run) = runs
+        .iter()
+        .position(|(start, end)| start.xz().distance(end.xz()) < f32::EPSILON)
+    {
+        return Err(WallError::ZeroLengthRun(run));
+    }
+    for (index, opening) in path.openings.iter().enumerate() {
+        let Some((start, end)) = runs.get(opening.run) else {
+            return Err(WallError::OpeningOutOfRange(opening.run));
+        };
+        let half_width = opening.width / 2.0;
+        if opening.width <= 0.0
+            || opening.center - half_width < 0.0
+            || opening.center + half_width > start.xz().distance(end.xz())
+        {
+            return Err(WallError::OpeningOutOfRange(opening.run));
+        }
+        if let OpeningKind::Window { sill, height } = opening.kind {
+            if sill < 0.0 || height <= 0.0 || sill + height > WALL_SEGMENT_HEIGHT {
+                return Err(WallError::WindowDoesNotFit(opening.run));
+            }
+        }
+        let covered = segments_covered(*start, *end, opening);
+        let overlaps = path.openings[..index]
+            .iter()
+            .filter(|other| other.run == opening.run)
+            .any(|other| {
+                let other_covered = segments_covered(*start, *end, other);
+                covered.start < other_covered.end && other_covered.start < covered.end
+            });
+        if overlaps {
+            return Err(WallError::OverlappingOpenings(opening.run));
+        }
+    }
+    Ok(runs)
+}
+
+fn segment_count(start: Vec3, end: Vec3) -> usize {
+    (start.xz().distance(end.xz()) / WALL_SEGMENT_WIDTH)
+        .ceil()
+        .max(1.0) as usize
+}
+
+/// Wall segments an opening takes out of its run, as indices from the start
+fn segments_covered(start: Vec3, end: Vec3, opening: &WallOpening) -> Range<usize> {
+    let num_segments = segment_count(start, end);
+    let segment_width = start.xz().distance(end.xz()) / num_segments as f32;
+    let from = ((opening.center - opening.width / 2.0) / segment_width).floor() as usize;
+    let to = ((opening.center + opening.width / 2.0) / segment_width).ceil() as usize;
+    from.min(num_segments)..to.min(num_segments)
+}
+
+fn spawn_wall_run(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    start: Vec3,
+    end: Vec3,
+    openings: &[(Range<usize>, OpeningKind)],
+    entities: &mut Vec<Entity>,
+) {
+    let length = start.xz().distance(end.xz());
+    let direction = (end.xz() - start.xz()) / length;
+    let angle = direction.x.atan2(direction.y);
+    let rotation = Quat::from_rotation_y(angle + PI / 2.);
+    let num_segments = segment_count(start, end);
+    let segment_width = length / num_segments as f32;
+    let stretch = segment_width / WALL_SEGMENT_WIDTH;
+    let opening_at = |i: usize| {
+        openings
+            .iter()
+            .find(|(covered, _)| covered.contains(&i))
+            .map(|(_, kind)| *kind)
+    };
+
+    commands
+        .spawn((Name::new("Wall"), Transform::from_translation(start)))
+        .with_children(|parent| {
+            for i in 0..num_segments {
+                // Windows keep a squashed segment below and above them
+                let heights = match opening_at(i) {
+                    None => vec![0.0..WALL_SEGMENT_HEIGHT],
+                    Some(OpeningKind::Door) => continue,
+                    Some(OpeningKind::Window { sill, height }) => {
+                        vec![0.0..sill, sill + height..WALL_SEGMENT_HEIGHT]
+                    }
+                };
+                // Segments are modelled from their far end back along the run
+                let pos = direction * (i + 1) as f32 * segment_width;
+                for height in heights {
+                    if height.end <= height.start {
+                        continue;
+                    }
+                    let squash = (height.end - height.start) / WALL_SEGMENT_HEIGHT;
                     let mut wall_entity_ec = parent.spawn((
-                        SceneRoot(asset_server.load("models/SM_Bld_Base_Wall_01.glb#Scene0")),
-                        Transform::from_xyz(pos.x, start.y, pos.y).with_rotation(rotation),
+                        SceneRoot(asset_server.load(WALL_SCENE_PATH)),
+                        Transform::from_xyz(pos.x, height.start, pos.y)
+                            .with_rotation(rotation)
+                            .with_scale(Vec3::new(stretch, squash, 1.0)),
                     ));
                     entities.push(wall_entity_ec.id());
-                    if i % 4 == 0 {
-                        wall_entity_ec
-                            .with_child((
-                                SceneRoot(
-                                    asset_server.load("models/SM_Prop_Lighting_Wall_03.glb#Scene0"),
-                                ),
-                                Transform::from_xyz(
-                                    0.0,
-                                    WALL_SEGMENT_HEIGHT - WALL_LIGHT_MARGIN,
-                                    WALL_SEGMENT_DEPTH / 2.0,
-                                ),
-                            ))
-                            .with_child((
-                                PointLight {
-                                    intensity: 10000.0,
-                                    ..default()
-                                },
-                                Transform::from_xyz(
-                                    0.0,
-                                    WALL_SEGMENT_HEIGHT - WALL_LIGHT_MARGIN,
-                                    0.5,
-                                ),
-                            ));
+                    if opening_at(i).is_none() && (i + 1) % 4 == 0 {
+                        // Lit by the store's lighting plan
+                        wall_entity_ec.with_child((
+                            SceneRoot(asset_server.load(WALL_LIGHT_SCENE_PATH)),
+                            Transform::from_xyz(
+                                0.0,
+                                WALL_SEGMENT_HEIGHT - WALL_LIGHT_MARGIN,
+                                WALL_SEGMENT_DEPTH / 2.0,
+                            ),
+                        ));
                     }
                 }
-                let mid_point = direction * length / 2.0;
-                parent.spawn((
-                    get_cuboid(length, num_segments),
-                    Transform::from_xyz(
-                        mid_point.x,
-                        start.y + WALL_SEGMENT_HEIGHT / 2.0,
-                        mid_point.y,
+            }
+
+            // One collider per stretch of solid wall, plus sill and lintel around windows
+            let mut i = 0;
+            while i < num_segments {
+                let kind = opening_at(i);
+                let run_end = (i..num_segments)
+                    .find(|j| opening_at(*j) != kind)
+                    .unwrap_or(num_segments);
+                let along = i as f32 * segment_width..run_end as f32 * segment_width;
+                match kind {
+                    None => spawn_wall_collider(
+                        parent,
+                        direction,
+                        rotation,
+                        along,
+                        0.0..WALL_SEGMENT_HEIGHT,
                     ),
-                ));
-            });
-        Ok(entities)
+                    Some(OpeningKind::Door) => {}
+                    Some(OpeningKind::Window { sill, height }) => {
+                        spawn_wall_collider(parent, direction, rotation, along.clone(), 0.0..sill);
+                        spawn_wall_collider(
+                            parent,
+                            direction,
+                            rotation,
+                            along,
+                            sill + height..WALL_SEGMENT_HEIGHT,
+                        );
+                    }
+                }
+                i = run_end;
+            }
+        });
+}
+
+fn spawn_wall_collider(
+    parent: &mut ChildBuilder,
+    direction: Vec2,
+    rotation: Quat,
+    along: Range<f32>,
+    height: Range<f32>,
+) {
+    let half_length = (along.end - along.start) / 2.0;
+    let half_height = (height.end - height.start) / 2.0;
+    if half_length <= 0.0 || half_height <= 0.0 {
+        return;
     }
+    let middle = direction * (along.start + half_length);
+    parent.spawn((
+        Collider::cuboid(half_length, half_height, WALL_SEGMENT_DEPTH / 2.0),
+        Transform::from_xyz(middle.x, height.start + half_height, middle.y).with_rotation(rotation),
+    ));
 }
 
-fn get_cuboid(length: Vec2, num_segments: i32) -> Collider {
-    let wall_width = num_segments as f32 * WALL_SEGMENT_WIDTH;
-    if length.x > length.y {
-        Collider::cuboid(
-            wall_width / 2.0,
-            WALL_SEGMENT_HEIGHT / 2.0,
-            length.y / 2.0 + (WALL_SEGMENT_DEPTH / 2.0),
-        )
-    } else {
-        Collider::cuboid(
-            length.x / 2.0 + (WALL_SEGMENT_DEPTH / 2.0),
-            WALL_SEGMENT_HEIGHT / 2.0,
-            wall_width / 2.0,
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_wall_paths_are_rejected() {
+        let check = |path: WallPath| validate(&path).map(|_| ());
+        assert_eq!(
+            check(WallPath::open(vec![Vec3::ZERO])),
+            Err(WallError::TooFewPoints(1))
+        );
+        assert_eq!(
+            check(WallPath::open(vec![Vec3::ZERO, Vec3::new(5.0, 1.0, 0.0)])),
+            Err(WallError::UnevenHeight {
+                point: 1,
+                y: 1.0,
+                expected: 0.0
+            })
+        );
+        let door = WallOpening {
+            run: 0,
+            center: 2.0,
+            width: 1.0,
+            kind: OpeningKind::Door,
+        };
+        assert_eq!(
+            check(
+                WallPath::open(vec![Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0)])
+                    .with_opening(door)
+                    .with_opening(WallOpening {
+                        center: 3.0,
+                        ..door
+                    })
+            ),
+            Err(WallError::OverlappingOpenings(0))
+        );
+        assert_eq!(
+            check(
+                WallPath::open(vec![Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0)])
+                    .with_opening(WallOpening { run: 1, ..door })
+            ),
+            Err(WallError::OpeningOutOfRange(1))
+        );
+        assert_eq!(
+            check(
+                WallPath::open(vec![Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0)]).with_opening(
+                    WallOpening {
+                        kind: OpeningKind::Window {
+                            sill: 1.0,
+                            height: WALL_SEGMENT_HEIGHT,
+                        },
+                        ..door
+                    }
+                )
+            ),
+            Err(WallError::WindowDoesNotFit(0))
+        );
+    }
+
+    #[test]
+    fn doorways_are_left_out_of_solid_runs() {
+        let path = WallPath::closed(vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 10.0),
+            Vec3::new(0.0, 0.0, 10.0),
+        ])
+        .with_opening(WallOpening {
+            run: 0,
+            center: 5.0,
+            width: 2.0,
+            kind: OpeningKind::Door,
+        })
+        .with_opening(WallOpening {
+            run: 2,
+            center: 5.0,
+            width: 2.0,
+            kind: OpeningKind::Window {
+                sill: 1.0,
+                height: 1.0,
+            },
+        });
+        let solid = path.solid_runs();
+        // Windows are still wall from above
+        assert_eq!(solid.len(), 5);
+        // The door takes out both 2.5m segments it overlaps
+        assert_eq!(solid[0], (Vec3::ZERO, Vec3::new(2.5, 0.0, 0.0)));
+        assert_eq!(
+            solid[1],
+            (Vec3::new(7.5, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            path.doorways(),
+            vec![(Vec3::new(2.5, 0.0, 0.0), Vec3::new(7.5, 0.0, 0.0))]
+        );
+        assert_eq!(
+            solid[4],
+            (Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 0.0))
+        );
     }
 }
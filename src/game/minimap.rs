@@ -0,0 +1,258 @@
+use crate::game::cart::ItemInCart;
+use crate::game::game::American;
+use crate::game::item::{ItemPickup, ItemPickupCountry};
+use crate::game::map::generator::StoreLayout;
+use crate::game::map::wall::WallPath;
+use crate::game::player::Player;
+use crate::state::InGameState;
+use bevy::app::App;
+use bevy::color::{Color, LinearRgba};
+use bevy::core::Name;
+use bevy::math::{EulerRot, Mat2, Vec2, Vec3Swizzles};
+use bevy::prelude::{
+    default, in_state, resource_exists, BackgroundColor, BuildChildren, Bundle, ChildBuild,
+    Commands, Component, Entity, GlobalTransform, IntoSystemConfigs, Node, OnTransition, Plugin,
+    PositionType, Quat, Query, Res, Transform, Update, Val, Visibility, With, Without,
+};
+
+const MINIMAP_HEIGHT: f32 = 240.0;
+const MINIMAP_RIGHT: f32 = 10.0;
+/// Leaves room for the version text underneath
+const MINIMAP_BOTTOM: f32 = 40.0;
+const WALL_THICKNESS: f32 = 2.0;
+/// Most markers drawn at once. Items are left off first.
+const MAX_MARKERS: usize = 48;
+/// How far from the player items are shown
+const ITEM_MARKER_RANGE: f32 = 20.0;
+/// Items worth at least this much at checkout are shown
+const HIGH_VALUE_SCORE: i32 = 5;
+
+pub struct MinimapPlugin;
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnTransition {
+                exited: InGameState::None,
+                entered: InGameState::Playing,
+            },
+            setup_minimap.run_if(resource_exists::<StoreLayout>),
+        );
+        app.add_systems(
+            Update,
+            update_minimap.run_if(in_state(InGameState::Playing)),
+        );
+    }
+}
+
+/// The store seen from above with -Z up, matching the top down camera
+#[derive(Component)]
+struct Minimap {
+    /// Store position at the map's top left corner, in xz
+    origin: Vec2,
+    /// Map pixels per meter
+    scale: f32,
+    /// Dots reused for whatever moves, in draw order
+    markers: Vec<Entity>,
+}
+impl Minimap {
+    fn to_map(&self, xz: Vec2) -> Vec2 {
+        (xz - self.origin) * self.scale
+    }
+
+    /// Center, size and rotation of a thin rect along each solid run of `walls`, so diagonal
+    /// walls are drawn as the line they are rather than the box around it
+    fn wall_rects(&self, walls: &WallPath) -> Vec<(Vec2, Vec2, f32)> {
+        walls
+            .solid_runs()
+            .into_iter()
+            .map(|(start, end)| {
+                let (start, end) = (self.to_map(start.xz()), self.to_map(end.xz()));
+                let along = end - start;
+                (
+                    (start + end) / 2.0,
+                    Vec2::new(along.length() + WALL_THICKNESS, WALL_THICKNESS),
+                    along.y.atan2(along.x),
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Component)]
+struct MinimapMarker;
+
+fn map_rect(center: Vec2, size: Vec2, color: Color) -> impl Bundle {
+    (
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(center.x - size.x / 2.0),
+            top: Val::Px(center.y - size.y / 2.0),
+            width: Val::Px(size.x),
+            height: Val::Px(size.y),
+            ..default()
+        },
+        BackgroundColor(color),
+    )
+}
+
+fn setup_minimap(mut commands: Commands, layout: Res<StoreLayout>) {
+    let mut minimap = Minimap {
+        origin: layout.center - layout.half_extents,
+        scale: MINIMAP_HEIGHT / (2.0 * layout.half_extents.y),
+        markers: vec![],
+    };
+    let size = 2.0 * layout.half_extents * minimap.scale;
+    let mut minimap_ec = commands.spawn((
+        Name::new("Minimap"),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(MINIMAP_RIGHT),
+            bottom: Val::Px(MINIMAP_BOTTOM),
+            width: Val::Px(size.x),
+            height: Val::Px(size.y),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.1, 0.1, 0.12, 0.8)),
+    ));
+    minimap_ec.with_children(|parent| {
+        for placed in layout.fixtures.iter() {
+            let yaw = placed.transform.rotation.to_euler(EulerRot::YXZ).0;
+            let half_extents = Mat2::from_angle(yaw).abs() * placed.fixture.half_extents();
+            // Departments in their aisle lighting colors, so they can be told apart
+            let color = placed
+                .department
+                .map_or(Color::srgb(0.5, 0.5, 0.5), |department| {
+                    department.zone_color()
+                });
+            parent.spawn(map_rect(
+                minimap.to_map(placed.transform.translation.xz()),
+                2.0 * half_extents * minimap.scale,
+                color,
+            ));
+        }
+        for (center, size, angle) in minimap.wall_rects(&layout.walls) {
+            parent.spawn((
+                map_rect(center, size, Color::srgb(0.85, 0.85, 0.8)),
+                Transform::from_rotation(Quat::from_rotation_z(angle)),
+            ));
+        }
+        parent.spawn(map_rect(
+            minimap.to_map(layout.checkout.translation.xz()),
+            Vec2::splat(8.0),
+            Color::srgb(0.2, 0.5, 1.0),
+        ));
+        for _ in 0..MAX_MARKERS {
+            let marker = parent
+                .spawn((
+                    map_rect(Vec2::ZERO, Vec2::ZERO, Color::NONE),
+                    Visibility::Hidden,
+                    MinimapMarker,
+                ))
+                .id();
+            minimap.markers.push(marker);
+        }
+    });
+    minimap_ec.insert(minimap);
+}
+
+/// Highlight colors are dim emissive tints, so are brought up to full strength for the map
+fn item_marker_color(country: &ItemPickupCountry) -> Color {
+    let highlight = country.highlight_color();
+    let brightest = highlight
+        .red
+        .max(highlight.green)
+        .max(highlight.blue)
+        .max(f32::EPSILON);
+    Color::LinearRgba(LinearRgba::rgb(
+        highlight.red / brightest,
+        highlight.green / brightest,
+        highlight.blue / brightest,
+    ))
+}
+
+fn update_minimap(
+    minimap_q: Query<&Minimap>,
+    player_q: Query<&GlobalTransform, With<Player>>,
+    npc_q: Query<&GlobalTransform, With<American>>,
+    item_q: Query<(&GlobalTransform, &ItemPickupCountry), (With<ItemPickup>, Without<ItemInCart>)>,
+    mut marker_q: Query<(&mut Node, &mut BackgroundColor, &mut Visibility), With<MinimapMarker>>,
+) {
+    let Ok(minimap) = minimap_q.get_single() else {
+        return;
+    };
+    let player = player_q
+        .get_single()
+        .ok()
+        .map(|player_t| player_t.translation().xz());
+
+    // Position, size and color, drawn in order so the player ends up on top
+    let mut markers: Vec<(Vec2, f32, Color)> = vec![];
+    let npcs: Vec<(Vec2, f32, Color)> = npc_q
+        .iter()
+        .map(|npc_t| (npc_t.translation().xz(), 6.0, Color::srgb(1.0, 0.3, 0.1)))
+        .collect();
+    if let Some(player) = player {
+        let mut items: Vec<(f32, Vec2, &ItemPickupCountry)> = item_q
+            .iter()
+            .filter(|(_, country)| country.scores() >= HIGH_VALUE_SCORE)
+            .map(|(item_t, country)| {
+                let xz = item_t.translation().xz();
+                (xz.distance_squared(player), xz, country)
+            })
+            .filter(|(distance_squared, _, _)| {
+                *distance_squared < ITEM_MARKER_RANGE * ITEM_MARKER_RANGE
+            })
+            .collect();
+        items.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let room = MAX_MARKERS.saturating_sub(npcs.len() + 1);
+        markers.extend(
+            items
+                .into_iter()
+                .take(room)
+                .map(|(_, xz, country)| (xz, 4.0, item_marker_color(country))),
+        );
+    }
+    markers.extend(npcs);
+    if let Some(player) = player {
+        markers.push((player, 8.0, Color::WHITE));
+    }
+
+    for (index, marker) in minimap.markers.iter().enumerate() {
+        let Ok((mut node, mut background, mut visibility)) = marker_q.get_mut(*marker) else {
+            continue;
+        };
+        let Some((xz, size, color)) = markers.get(index) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let center = minimap.to_map(*xz);
+        node.left = Val::Px(center.x - size / 2.0);
+        node.top = Val::Px(center.y - size / 2.0);
+        node.width = Val::Px(*size);
+        node.height = Val::Px(*size);
+        background.0 = *color;
+        *visibility = Visibility::Inherited;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::Vec3;
+
+    #[test]
+    fn diagonal_walls_are_drawn_as_thin_rotated_rects() {
+        let minimap = Minimap {
+            origin: Vec2::ZERO,
+            scale: 2.0,
+            markers: vec![],
+        };
+        let walls = WallPath::open(vec![Vec3::ZERO, Vec3::new(3.0, 0.0, 4.0)]);
+        let rects = minimap.wall_rects(&walls);
+        assert_eq!(rects.len(), 1);
+        let (center, size, angle) = rects[0];
+        assert!(center.distance(Vec2::new(3.0, 4.0)) < 1e-4);
+        assert!(size.distance(Vec2::new(10.0 + WALL_THICKNESS, WALL_THICKNESS)) < 1e-4);
+        assert!((angle - 4.0_f32.atan2(3.0)).abs() < 1e-4);
+    }
+}
@@ -1,8 +1,19 @@
 mod animation;
-mod effects;
+mod cart;
+mod checkout;
+pub mod effects;
 pub mod game;
+pub mod headless;
 mod hud;
+mod input;
 mod item;
-mod map;
+pub mod loading;
+pub mod map;
+mod minimap;
 mod movement;
 pub mod player;
+pub mod recording;
+mod replay;
+mod rng;
+mod scene_collider;
+mod stock;
@@ -1,56 +1,36 @@
-use crate::camera::GameCamera;
-use crate::game::animation::{AnimationPlayerEntityForRootEntity, AnimationToPlay};
+use crate::game::cart::CartLoad;
+use crate::game::input::{PlayerInput, PlayerInputSet};
 use crate::game::player::Player;
 use crate::state::InGameState;
-use bevy::animation::RepeatAnimation;
 use bevy::app::App;
-use bevy::color::palettes::basic::WHITE;
-use bevy::math::{Vec2, Vec3};
+use bevy::math::Vec3;
 use bevy::prelude::{
-    debug, in_state, info, warn, AnimationPlayer, AnimationTransitions, Assets, ButtonInput,
-    Camera, Children, Command, Commands, Component, Dir2, Entity, FixedUpdate, FloatExt, FromWorld,
-    Handle, IntoSystemConfigs, KeyCode, Material, Mesh, Mesh3d, MeshMaterial3d, Plugin, Quat,
-    Query, Reflect, Res, Resource, Sphere, StableInterpolate, StandardMaterial, Timer, TimerMode,
-    Transform, Update, Vec3Swizzles, With, Without, World,
+    in_state, Component, EulerRot, FixedUpdate, FloatExt, IntoSystemConfigs, Plugin, Quat, Query,
+    Reflect, Res, SystemSet, Timer, TimerMode, Transform, Vec3Swizzles, With,
 };
 use bevy::time::Time;
 use bevy_inspector_egui::prelude::*;
 use bevy_inspector_egui::InspectorOptions;
-use bevy_rapier3d::prelude::{ExternalForce, ExternalImpulse, Velocity};
-use rand::{rng, Rng};
-use std::ops::{Deref, Div};
-use std::time::Duration;
+use bevy_rapier3d::prelude::{ExternalImpulse, LockedAxes, Velocity};
 
 pub struct MovementPlugin;
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             FixedUpdate,
-            (handle_movement, apply_stubborn_force).run_if(in_state(InGameState::Playing)),
+            (apply_stubborn_force, handle_movement)
+                .chain()
+                .in_set(MovementSet)
+                .after(PlayerInputSet)
+                .run_if(in_state(InGameState::Playing)),
         );
         app.register_type::<MovementSettings>();
-        app.init_resource::<StepParticleAssets>();
     }
 }
 
-#[derive(Resource)]
-pub struct StepParticleAssets {
-    pub mesh: Handle<Mesh>,
-    pub material: Handle<StandardMaterial>,
-}
-impl FromWorld for StepParticleAssets {
-    fn from_world(world: &mut World) -> Self {
-        Self {
-            mesh: world.resource_mut::<Assets<Mesh>>().add(Sphere::new(10.0)),
-            material: world
-                .resource_mut::<Assets<StandardMaterial>>()
-                .add(StandardMaterial {
-                    base_color: WHITE.into(),
-                    ..Default::default()
-                }),
-        }
-    }
-}
+/// Systems reacting to how the cart moved this fixed tick should run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MovementSet;
 
 #[derive(Component, Reflect, InspectorOptions)]
 #[reflect(InspectorOptions)]
@@ -61,6 +41,28 @@ pub struct MovementSettings {
     pub low_speed_turn_factor: f32,
     pub high_speed_turn_factor: f32,
     pub stubborn_cart_strength: f32,
+    /// Yaw rate in radians per second at a turn factor of 1
+    pub turn_rate: f32,
+    /// How quickly the fixed back wheels stop the cart sliding sideways
+    pub grip: f32,
+    /// Back wheel grip while the handbrake is held, so the back end slides out
+    pub drift_grip: f32,
+    /// How quickly the handbrake slows the cart
+    pub handbrake_brake: f32,
+    /// How strongly the front casters swing the cart round to follow where it's going
+    pub caster_alignment: f32,
+    /// Speed lost in a single tick that knocks the cart over
+    pub tip_impact_speed: f32,
+    pub tip_spin: f32,
+    pub tip_recovery_secs: f32,
+    /// Notional mass of the empty cart. Pushing and turning scale by this over the loaded mass
+    pub empty_cart_mass: f32,
+    /// Speed lost in a single tick that throws items out of the basket
+    pub spill_impact_speed: f32,
+    /// Fraction of the cart's velocity spilled items carry on with
+    pub spill_carry: f32,
+    /// Upward speed given to spilled items per unit of speed lost
+    pub spill_lift: f32,
 }
 impl Default for MovementSettings {
     fn default() -> Self {
@@ -71,134 +73,157 @@ impl Default for MovementSettings {
             low_speed_turn_factor: 0.3,
             high_speed_turn_factor: 1.2,
             stubborn_cart_strength: -0.5,
+            turn_rate: 3.0,
+            grip: 12.0,
+            drift_grip: 1.5,
+            handbrake_brake: 2.0,
+            caster_alignment: 4.0,
+            tip_impact_speed: 6.0,
+            tip_spin: 4.0,
+            tip_recovery_secs: 2.0,
+            empty_cart_mass: 0.1,
+            spill_impact_speed: 3.0,
+            spill_carry: 0.5,
+            spill_lift: 0.6,
         }
     }
 }
 
+/// Handling state carried between ticks.
+#[derive(Component, Default)]
+pub struct CartHandling {
+    last_linvel: Vec3,
+    /// Yaw rate the stubborn wheel is pulling the cart with this tick
+    stubborn_pull: f32,
+    /// Counts down while the cart is on its side
+    pub tipped: Option<Timer>,
+}
+
+pub fn cart_locked_axes() -> LockedAxes {
+    LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z
+}
+
 fn apply_stubborn_force(
-    mut query: Query<(&Transform, &Velocity, &mut ExternalForce, &MovementSettings), With<Player>>,
+    mut query: Query<(&Velocity, &mut CartHandling, &MovementSettings), With<Player>>,
+    time: Res<Time>,
 ) {
-    // FIXME
-    // for (transform, velocity, mut external_force, player_ms) in query.iter_mut() {
-    //     if velocity.linvel.length() > 1.0 {
-    //         // Apply force in that direction
-    //         external_force.torque += Vec3::Y * player_ms.stubborn_cart_strength;
-    //     }
-    // }
+    for (velocity, mut handling, player_ms) in query.iter_mut() {
+        let speed = velocity.linvel.xz().length();
+        handling.stubborn_pull = if speed > 1.0 {
+            // One wheel never quite lines up, so the cart drifts to one side and wobbles
+            let wobble = 1.0 + 0.5 * (time.elapsed_secs() * 3.0).sin();
+            player_ms.stubborn_cart_strength * (speed / player_ms.max_speed).min(1.0) * wobble
+        } else {
+            0.0
+        };
+    }
 }
 
 fn handle_movement(
-    mut commands: Commands,
-    keys: Res<ButtonInput<KeyCode>>,
+    input: Res<PlayerInput>,
     mut player_q: Query<
         (
-            Entity,
             &mut Transform,
             &mut Velocity,
             &mut ExternalImpulse,
+            &mut LockedAxes,
+            &mut CartHandling,
+            &CartLoad,
             &MovementSettings,
-            &Children,
-            &AnimationPlayerEntityForRootEntity,
         ),
         With<Player>,
     >,
-    player_animation_to_play_q: Query<(&AnimationToPlay), Without<Player>>,
-    mut animationp_q: Query<(&mut AnimationPlayer, &mut AnimationTransitions)>,
-    particle: Res<StepParticleAssets>,
     time: Res<Time>,
 ) {
-    let mut direction = Vec3::ZERO;
+    let Ok((
+        mut player_t,
+        mut player_velocity,
+        mut player_impulse,
+        mut locked_axes,
+        mut handling,
+        load,
+        player_ms,
+    )) = player_q.get_single_mut()
+    else {
+        return;
+    };
+    let dt = time.delta_secs();
+    let last_linvel = handling.last_linvel;
+    handling.last_linvel = player_velocity.linvel;
 
-    if keys.pressed(KeyCode::KeyW) {
-        direction -= Vec3::Z;
+    if let Some(timer) = &mut handling.tipped {
+        if timer.tick(time.delta()).finished() {
+            // Stand back up facing the same way
+            let (yaw, _, _) = player_t.rotation.to_euler(EulerRot::YXZ);
+            player_t.rotation = Quat::from_rotation_y(yaw);
+            player_velocity.angvel = Vec3::ZERO;
+            *locked_axes = cart_locked_axes();
+            handling.tipped = None;
+        }
+        return;
     }
-    if keys.pressed(KeyCode::KeyS) {
-        direction += Vec3::Z;
+
+    let speed = player_velocity.linvel.xz().length();
+    if last_linvel.xz().length() - speed > player_ms.tip_impact_speed {
+        // Tip over away from whatever was hit
+        let tip_axis = Vec3::Y.cross(last_linvel.with_y(0.0)).normalize_or_zero();
+        *locked_axes = LockedAxes::empty();
+        player_velocity.angvel += tip_axis * player_ms.tip_spin;
+        handling.tipped = Some(Timer::from_seconds(
+            player_ms.tip_recovery_secs,
+            TimerMode::Once,
+        ));
+        return;
     }
-    if keys.pressed(KeyCode::KeyA) {
-        direction -= Vec3::X;
+
+    let forward = *player_t.forward();
+    let right = *player_t.right();
+    let throttle = input.forward as i32 as f32 - input.back as i32 as f32;
+    let steer = input.left as i32 as f32 - input.right as i32 as f32;
+    // A full basket is harder to get going and to swing round
+    let load_factor = player_ms.empty_cart_mass / (player_ms.empty_cart_mass + load.mass);
+
+    if throttle != 0.0 && speed < player_ms.max_speed {
+        player_impulse.impulse +=
+            forward * throttle * player_ms.speed * load_factor * if input.run { 2.0 } else { 1.0 };
     }
-    if keys.pressed(KeyCode::KeyD) {
-        direction += Vec3::X;
+
+    // Back wheels are fixed, so they resist sliding sideways unless the handbrake is on
+    let grip = if input.handbrake {
+        player_ms.drift_grip
+    } else {
+        player_ms.grip
+    };
+    let lateral_speed = player_velocity.linvel.dot(right);
+    player_velocity.linvel -= right * lateral_speed * (1.0 - (-grip * dt).exp());
+    if input.handbrake {
+        let forward_speed = player_velocity.linvel.dot(forward);
+        player_velocity.linvel -=
+            forward * forward_speed * (1.0 - (-player_ms.handbrake_brake * dt).exp());
     }
 
-    match player_q.get_single_mut() {
-        Ok((
-            player_e,
-            mut player_t,
-            mut player_velocity,
-            mut player_impulse,
-            player_ms,
-            player_children,
-            animation_player_link,
-        )) => {
-            if direction != Vec3::ZERO {
-                direction = player_t.rotation * direction.normalize();
-                let mut impulse_force = direction
-                    * player_ms.speed
-                    * if keys.pressed(KeyCode::ShiftLeft) {
-                        2.0
-                    } else {
-                        1.0
-                    };
-                if player_velocity.linvel.length() < player_ms.max_speed {
-                    player_impulse.impulse += impulse_force;
-                }
-                if player_velocity.linvel.length_squared() > 0.1
-                    || player_velocity.angvel.length_squared() > 0.1
-                {
-                    let facing_direction = player_velocity.linvel.normalize();
-                    let forward_dot = facing_direction.dot(player_t.rotation * Vec3::NEG_Z);
-                    // Flip the target direction if moving backward
-                    let adjusted_direction = if forward_dot < -0.1 {
-                        -Vec3::new(facing_direction.x, 0.0, facing_direction.z)
-                    } else {
-                        Vec3::new(facing_direction.x, 0.0, facing_direction.z)
-                    };
-                    let target_rotation = Quat::from_rotation_arc(Vec3::NEG_Z, adjusted_direction);
-                    player_t.rotation = player_t
-                        .rotation
-                        .lerp(target_rotation, 1.0 - (-time.delta_secs() * 5.0).exp());
-                }
-            }
+    // Turning gets sharper with speed
+    let speed_fraction = (speed / player_ms.max_speed).clamp(0.0, 1.0);
+    let turn_factor = player_ms.low_speed_turn_factor.lerp(
+        player_ms.high_speed_turn_factor,
+        speed_fraction.powf(player_ms.turn_speed_exp),
+    );
+    let reversing = player_velocity.linvel.dot(forward) < -0.1;
+    let mut target_yaw_rate = steer * player_ms.turn_rate * turn_factor * load_factor;
+    if reversing {
+        target_yaw_rate = -target_yaw_rate;
+    }
 
-            // Find animation to play in player entity, then find animation player in entity tree to play animation
-            if let Some(player_animation) = player_children
-                .iter()
-                .find_map(|&child| player_animation_to_play_q.get(child).ok())
-            {
-                if let Ok((mut animation_p, mut transitions)) =
-                    animationp_q.get_mut(animation_player_link.0)
-                {
-                    let opt_animation = animation_p.animation_mut(player_animation.index);
-                    if player_velocity.linvel.length_squared() > 1.0 {
-                        match opt_animation {
-                            Some(animation) if animation.is_paused() => {
-                                animation.resume();
-                            }
-                            None => {
-                                transitions
-                                    .play(
-                                        &mut animation_p,
-                                        player_animation.index,
-                                        Duration::from_millis(250),
-                                    )
-                                    .repeat();
-                            }
-                            _ => {}
-                        }
-                    } else {
-                        if let Some(animation) = opt_animation {
-                            animation.pause().set_seek_time(0.0);
-                        }
-                    }
-                } else {
-                    warn!("can't find animation player from link");
-                }
-            } else {
-                warn!("can't find animation to play under player entity");
-            }
-        }
-        _ => {}
+    // Front casters swivel to follow the cart's velocity, swinging it round to face that way
+    if speed > 0.1 && !input.handbrake {
+        let travel = if reversing { -forward } else { forward };
+        let heading_error = -travel.xz().angle_to(player_velocity.linvel.xz());
+        target_yaw_rate += heading_error * player_ms.caster_alignment * speed_fraction;
     }
+    target_yaw_rate += handling.stubborn_pull;
+
+    player_velocity.angvel.y = player_velocity
+        .angvel
+        .y
+        .lerp(target_yaw_rate, 1.0 - (-10.0 * dt).exp());
 }
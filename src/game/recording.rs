@@ -0,0 +1,256 @@
+use crate::game::checkout::RoundFinished;
+use crate::game::game::{GameSeed, ScoreResource};
+use crate::game::input::PlayerInput;
+use crate::game::item::ItemId;
+use crate::game::map::generator::generate_store_layout;
+use crate::state::{AppState, InGameState};
+use bevy::app::{App, AppExit};
+use bevy::prelude::{
+    in_state, info, warn, Commands, Event, EventReader, EventWriter, FixedPostUpdate,
+    IntoSystemConfigs, Last, OnEnter, Plugin, PreStartup, Res, ResMut, Resource,
+};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+pub struct InputRecordingPlugin;
+impl Plugin for InputRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMode>();
+        app.init_resource::<InputRecording>();
+        app.add_systems(PreStartup, setup_input_recording);
+        app.add_systems(
+            OnEnter(AppState::Loading),
+            pick_run_seed.before(generate_store_layout),
+        );
+        app.add_event::<PlaybackFinished>();
+        app.add_systems(
+            FixedPostUpdate,
+            (report_playback_finished).run_if(in_state(InGameState::Playing)),
+        );
+        app.add_systems(Last, save_input_recording);
+    }
+}
+
+/// Where player input comes from, chosen with `--record <path>` or `--playback <path>`.
+#[derive(Resource, Default, Clone, Debug, PartialEq)]
+pub enum InputMode {
+    #[default]
+    Live,
+    Record(PathBuf),
+    Playback(PathBuf),
+}
+impl InputMode {
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let arg_value = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|index| args.get(index + 1))
+                .map(PathBuf::from)
+        };
+        if let Some(path) = arg_value("--playback") {
+            InputMode::Playback(path)
+        } else if let Some(path) = arg_value("--record") {
+            InputMode::Record(path)
+        } else {
+            InputMode::Live
+        }
+    }
+
+    /// Recording and playback need physics stepped on the fixed schedule to be repeatable.
+    pub fn is_deterministic(&self) -> bool {
+        *self != InputMode::Live
+    }
+}
+
+fn seed_from_args() -> Option<u64> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|index| args.get(index + 1))
+        .and_then(|seed| seed.parse().ok())
+}
+
+/// Sent once the last recorded tick has been played back
+#[derive(Event)]
+pub struct PlaybackFinished;
+
+#[derive(Resource, Default)]
+pub struct InputRecording {
+    pub seed: u64,
+    pub ticks: Vec<PlayerInput>,
+    cursor: usize,
+}
+impl InputRecording {
+    pub fn next_tick(&mut self) -> Option<PlayerInput> {
+        let tick = self.ticks.get(self.cursor).cloned();
+        if tick.is_some() {
+            self.cursor += 1;
+        }
+        tick
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.ticks.len()
+    }
+
+    // One line per fixed tick: held keys as letters, or `.` for none, then the id of any item hooked
+    pub fn serialize(&self) -> String {
+        let mut out = format!("seed {}\n", self.seed);
+        for tick in &self.ticks {
+            let mut keys = String::new();
+            for (held, key) in [
+                (tick.forward, 'W'),
+                (tick.back, 'S'),
+                (tick.left, 'A'),
+                (tick.right, 'D'),
+                (tick.run, 'R'),
+                (tick.handbrake, 'B'),
+                (tick.stomp, 'X'),
+            ] {
+                if held {
+                    keys.push(key);
+                }
+            }
+            if keys.is_empty() {
+                keys.push('.');
+            }
+            out.push_str(&keys);
+            if let Some(hook) = tick.hook {
+                let _ = write!(out, " {}", hook.0);
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let mut lines = data.lines();
+        let seed = lines
+            .next()
+            .and_then(|line| line.strip_prefix("seed "))
+            .ok_or("Recording must start with a seed line")?
+            .trim()
+            .parse()
+            .map_err(|e| format!("Invalid seed: {e}"))?;
+        let mut ticks = vec![];
+        for (line_number, line) in lines.enumerate() {
+            let mut parts = line.split_whitespace();
+            let keys = parts.next().unwrap_or(".");
+            let hook = parts
+                .next()
+                .map(|part| part.parse().map(ItemId))
+                .transpose()
+                .map_err(|e| format!("Invalid hook target on tick {line_number}: {e}"))?;
+            if parts.next().is_some() {
+                return Err(format!(
+                    "Hook target on tick {line_number} needs one item id"
+                ));
+            }
+            ticks.push(PlayerInput {
+                forward: keys.contains('W'),
+                back: keys.contains('S'),
+                left: keys.contains('A'),
+                right: keys.contains('D'),
+                run: keys.contains('R'),
+                handbrake: keys.contains('B'),
+                stomp: keys.contains('X'),
+                hook,
+            });
+        }
+        Ok(InputRecording {
+            seed,
+            ticks,
+            cursor: 0,
+        })
+    }
+}
+
+fn setup_input_recording(mut commands: Commands, mut input_mode: ResMut<InputMode>) {
+    let seed = seed_from_args().unwrap_or_else(rand::random);
+    let recording = match &*input_mode {
+        InputMode::Playback(path) => {
+            match std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| InputRecording::parse(&data))
+            {
+                Ok(recording) => {
+                    info!(
+                        "playing back {} ticks from {}",
+                        recording.ticks.len(),
+                        path.display()
+                    );
+                    recording
+                }
+                Err(e) => {
+                    warn!("failed to load input recording {}: {}", path.display(), e);
+                    *input_mode = InputMode::Live;
+                    InputRecording {
+                        seed,
+                        ..Default::default()
+                    }
+                }
+            }
+        }
+        _ => InputRecording {
+            seed,
+            ..Default::default()
+        },
+    };
+    info!("game seed: {}", recording.seed);
+    commands.insert_resource(GameSeed(recording.seed));
+    commands.insert_resource(recording);
+}
+
+// A new store every run, unless it has to match a recording or the seed was chosen
+fn pick_run_seed(input_mode: Res<InputMode>, mut seed: ResMut<GameSeed>) {
+    if input_mode.is_deterministic() || seed_from_args().is_some() {
+        return;
+    }
+    seed.0 = rand::random();
+    info!("game seed: {}", seed.0);
+}
+
+fn report_playback_finished(
+    mut input_mode: ResMut<InputMode>,
+    recording: Res<InputRecording>,
+    score_res: Res<ScoreResource>,
+    mut finished_events: EventWriter<PlaybackFinished>,
+) {
+    if let InputMode::Playback(path) = &*input_mode {
+        if recording.is_finished() {
+            info!(
+                "finished playing back {} after {} ticks, final score: {}",
+                path.display(),
+                recording.ticks.len(),
+                score_res.score
+            );
+            *input_mode = InputMode::Live;
+            finished_events.send(PlaybackFinished);
+        }
+    }
+}
+
+// Saved after every round as well as on exit, so a run that never exits cleanly isn't lost
+fn save_input_recording(
+    mut exit_events: EventReader<AppExit>,
+    mut finished_events: EventReader<RoundFinished>,
+    input_mode: Res<InputMode>,
+    recording: Res<InputRecording>,
+) {
+    let exiting = exit_events.read().count() > 0;
+    let round_finished = finished_events.read().count() > 0;
+    if !exiting && !round_finished {
+        return;
+    }
+    if let InputMode::Record(path) = &*input_mode {
+        match std::fs::write(path, recording.serialize()) {
+            Ok(()) => info!(
+                "saved {} ticks of input to {}",
+                recording.ticks.len(),
+                path.display()
+            ),
+            Err(e) => warn!("failed to save input recording {}: {}", path.display(), e),
+        }
+    }
+}
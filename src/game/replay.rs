@@ -0,0 +1,327 @@
+use crate::camera::{CameraModeBlend, GameCamera};
+use crate::game::game::American;
+use crate::game::item::ItemIsStomped;
+use crate::game::player::{ItemCaught, Player};
+use crate::state::InGameState;
+use bevy::app::App;
+use bevy::input::ButtonInput;
+use bevy::prelude::{
+    in_state, Commands, Component, DespawnRecursiveExt, Entity, EventReader, Handle,
+    IntoSystemConfigs, KeyCode, NextState, Node, OnEnter, OnExit, Or, Plugin, PositionType, Quat,
+    Query, Reflect, ReflectResource, Res, ResMut, Resource, Scene, SceneRoot, Text, Time,
+    Transform, Update, Val, Vec3, Virtual, Visibility, With, Without,
+};
+use bevy::utils::HashMap;
+use bevy_egui::EguiPlugin;
+use bevy_inspector_egui::prelude::ReflectInspectorOptions;
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+use bevy_inspector_egui::InspectorOptions;
+use bevy_rapier3d::plugin::RapierConfiguration;
+use std::collections::VecDeque;
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (record_replay_frame, trigger_replay)
+                .chain()
+                .run_if(in_state(InGameState::Playing)),
+        );
+        app.add_systems(OnEnter(InGameState::Replay), start_replay);
+        app.add_systems(
+            Update,
+            (play_replay, orbit_replay_camera, skip_replay)
+                .chain()
+                .run_if(in_state(InGameState::Replay)),
+        );
+        app.add_systems(OnExit(InGameState::Replay), end_replay);
+        app.insert_resource(ReplayResource {
+            buffer_secs: 5.0,
+            slow_motion: 0.35,
+            auto_replay_min_score: 10,
+            orbit_speed: 0.6,
+            orbit_distance: 6.0,
+            orbit_height: 3.0,
+        });
+        app.init_resource::<ReplayBuffer>();
+        app.register_type::<ReplayResource>();
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(ResourceInspectorPlugin::<ReplayResource>::default());
+        }
+    }
+}
+
+#[derive(Resource, InspectorOptions, Reflect)]
+#[reflect(Resource, InspectorOptions)]
+pub struct ReplayResource {
+    buffer_secs: f32,
+    slow_motion: f32,
+    auto_replay_min_score: i32,
+    orbit_speed: f32,
+    orbit_distance: f32,
+    orbit_height: f32,
+}
+
+#[derive(Clone)]
+struct ReplayEntity {
+    entity: Entity,
+    transform: Transform,
+    // Kept so caught items, which are despawned by then, can be brought back as ghosts
+    scene: Option<Handle<Scene>>,
+}
+
+#[derive(Clone)]
+struct ReplayFrame {
+    time: f32,
+    entities: Vec<ReplayEntity>,
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayBuffer {
+    frames: VecDeque<ReplayFrame>,
+}
+
+#[derive(Resource)]
+struct ReplayPlayback {
+    frames: Vec<ReplayFrame>,
+    elapsed: f32,
+    orbit_angle: f32,
+    live_transforms: Vec<(Entity, Transform)>,
+    ghosts: HashMap<Entity, Entity>,
+}
+
+#[derive(Component)]
+struct ReplayGhost;
+
+#[derive(Component)]
+struct ReplayText;
+
+fn record_replay_frame(
+    mut buffer: ResMut<ReplayBuffer>,
+    tracked_q: Query<
+        (Entity, &Transform, Option<&SceneRoot>),
+        Or<(With<Player>, With<ItemIsStomped>, With<American>)>,
+    >,
+    replay_settings: Res<ReplayResource>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+    buffer.frames.push_back(ReplayFrame {
+        time: now,
+        entities: tracked_q
+            .iter()
+            .map(|(entity, transform, scene)| ReplayEntity {
+                entity,
+                transform: *transform,
+                scene: scene.map(|scene| scene.0.clone()),
+            })
+            .collect(),
+    });
+    while buffer
+        .frames
+        .front()
+        .is_some_and(|frame| now - frame.time > replay_settings.buffer_secs)
+    {
+        buffer.frames.pop_front();
+    }
+}
+
+fn trigger_replay(
+    mut caught_events: EventReader<ItemCaught>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buffer: Res<ReplayBuffer>,
+    replay_settings: Res<ReplayResource>,
+    mut in_game_state: ResMut<NextState<InGameState>>,
+) {
+    let high_value_catch = caught_events
+        .read()
+        .any(|caught| caught.stomped && caught.score >= replay_settings.auto_replay_min_score);
+    if (high_value_catch || keys.just_pressed(KeyCode::KeyR)) && buffer.frames.len() > 1 {
+        in_game_state.set(InGameState::Replay);
+    }
+}
+
+fn start_replay(
+    mut commands: Commands,
+    mut buffer: ResMut<ReplayBuffer>,
+    transform_q: Query<&Transform>,
+    mut rapier_config_q: Query<&mut RapierConfiguration>,
+    mut time: ResMut<Time<Virtual>>,
+    replay_settings: Res<ReplayResource>,
+) {
+    let frames: Vec<ReplayFrame> = buffer.frames.drain(..).collect();
+
+    // Remember where everything is now so the live game resumes untouched
+    let mut live_transforms = vec![];
+    let mut ghosts = HashMap::new();
+    for frame in &frames {
+        for replay_entity in &frame.entities {
+            if live_transforms
+                .iter()
+                .any(|(entity, _)| *entity == replay_entity.entity)
+                || ghosts.contains_key(&replay_entity.entity)
+            {
+                continue;
+            }
+            if let Ok(transform) = transform_q.get(replay_entity.entity) {
+                live_transforms.push((replay_entity.entity, *transform));
+            } else if let Some(scene) = &replay_entity.scene {
+                let ghost = commands
+                    .spawn((
+                        SceneRoot(scene.clone()),
+                        replay_entity.transform,
+                        Visibility::Hidden,
+                        ReplayGhost,
+                    ))
+                    .id();
+                ghosts.insert(replay_entity.entity, ghost);
+            }
+        }
+    }
+
+    for mut rapier_config in rapier_config_q.iter_mut() {
+        rapier_config.physics_pipeline_active = false;
+    }
+    time.set_relative_speed(replay_settings.slow_motion);
+    commands.spawn((
+        Text::new("REPLAY"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Percent(45.0),
+            ..Default::default()
+        },
+        ReplayText,
+    ));
+    commands.insert_resource(ReplayPlayback {
+        frames,
+        elapsed: 0.0,
+        orbit_angle: 0.0,
+        live_transforms,
+        ghosts,
+    });
+}
+
+fn play_replay(
+    mut playback: ResMut<ReplayPlayback>,
+    mut transform_q: Query<(&mut Transform, Option<&mut Visibility>), Without<GameCamera>>,
+    mut in_game_state: ResMut<NextState<InGameState>>,
+    time: Res<Time>,
+) {
+    // Virtual time is slowed down, so this plays back in slow motion
+    playback.elapsed += time.delta_secs();
+    let (Some(first), Some(last)) = (playback.frames.first(), playback.frames.last()) else {
+        in_game_state.set(InGameState::Playing);
+        return;
+    };
+    let replay_time = first.time + playback.elapsed;
+    if replay_time > last.time {
+        in_game_state.set(InGameState::Playing);
+        return;
+    }
+    let frame_index = playback
+        .frames
+        .partition_point(|frame| frame.time < replay_time)
+        .min(playback.frames.len() - 1);
+    let frame = &playback.frames[frame_index];
+
+    for (&recorded, &ghost) in playback.ghosts.iter() {
+        if let Ok((_, Some(mut visibility))) = transform_q.get_mut(ghost) {
+            let in_frame = frame
+                .entities
+                .iter()
+                .any(|replay_entity| replay_entity.entity == recorded);
+            *visibility = if in_frame {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+    for replay_entity in &frame.entities {
+        let entity = playback
+            .ghosts
+            .get(&replay_entity.entity)
+            .copied()
+            .unwrap_or(replay_entity.entity);
+        if let Ok((mut transform, _)) = transform_q.get_mut(entity) {
+            *transform = replay_entity.transform;
+        }
+    }
+}
+
+fn orbit_replay_camera(
+    mut playback: ResMut<ReplayPlayback>,
+    mut camera_q: Query<&mut Transform, With<GameCamera>>,
+    replay_settings: Res<ReplayResource>,
+    time: Res<Time>,
+) {
+    playback.orbit_angle += time.delta_secs() * replay_settings.orbit_speed;
+    let first_time = playback.frames.first().map_or(0.0, |frame| frame.time);
+    let replay_time = first_time + playback.elapsed;
+    let Some(frame) = playback
+        .frames
+        .iter()
+        .find(|frame| frame.time >= replay_time)
+    else {
+        return;
+    };
+    if frame.entities.is_empty() {
+        return;
+    }
+
+    // Orbit around the centre of everything recorded in this frame
+    let focus = frame
+        .entities
+        .iter()
+        .map(|replay_entity| replay_entity.transform.translation)
+        .sum::<Vec3>()
+        / frame.entities.len() as f32;
+    if let Ok(mut camera_t) = camera_q.get_single_mut() {
+        let offset = Quat::from_rotation_y(playback.orbit_angle)
+            * Vec3::new(
+                0.0,
+                replay_settings.orbit_height,
+                replay_settings.orbit_distance,
+            );
+        camera_t.translation = camera_t
+            .translation
+            .lerp(focus + offset, 1.0 - (-time.delta_secs() * 5.0).exp());
+        camera_t.look_at(focus + Vec3::new(0.0, 1.0, 0.0), Vec3::Y);
+    }
+}
+
+fn skip_replay(keys: Res<ButtonInput<KeyCode>>, mut in_game_state: ResMut<NextState<InGameState>>) {
+    if keys.just_pressed(KeyCode::KeyR) || keys.just_pressed(KeyCode::Escape) {
+        in_game_state.set(InGameState::Playing);
+    }
+}
+
+fn end_replay(
+    mut commands: Commands,
+    playback: Res<ReplayPlayback>,
+    mut transform_q: Query<&mut Transform, Without<GameCamera>>,
+    camera_q: Query<&Transform, With<GameCamera>>,
+    ghost_q: Query<Entity, Or<(With<ReplayGhost>, With<ReplayText>)>>,
+    mut rapier_config_q: Query<&mut RapierConfiguration>,
+    mut time: ResMut<Time<Virtual>>,
+    mut camera_blend: ResMut<CameraModeBlend>,
+) {
+    for (entity, live_transform) in &playback.live_transforms {
+        if let Ok(mut transform) = transform_q.get_mut(*entity) {
+            *transform = *live_transform;
+        }
+    }
+    for entity in ghost_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for mut rapier_config in rapier_config_q.iter_mut() {
+        rapier_config.physics_pipeline_active = true;
+    }
+    time.set_relative_speed(1.0);
+    if let Ok(camera_t) = camera_q.get_single() {
+        camera_blend.start(*camera_t);
+    }
+    commands.remove_resource::<ReplayPlayback>();
+}
@@ -0,0 +1,58 @@
+use crate::game::game::GameSeed;
+use crate::state::AppState;
+use bevy::app::App;
+use bevy::math::Vec3;
+use bevy::prelude::{info, OnEnter, Plugin, Res, ResMut, Resource};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// Offsets the cosmetic stream so it never mirrors the gameplay stream
+const COSMETIC_STREAM: u64 = 0x9E37_79B9_7F4A_7C15;
+
+pub struct GameRngPlugin;
+impl Plugin for GameRngPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), reseed_game_rng);
+        app.insert_resource(GameRng::from_seed(0));
+    }
+}
+
+/// All randomness for a run. Gameplay draws must come from [`GameRng::gameplay`] so a seed
+/// reproduces the same run; effects draw from [`GameRng::cosmetic`] so they can't disturb it.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    gameplay: StdRng,
+    cosmetic: StdRng,
+}
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        GameRng {
+            seed,
+            gameplay: StdRng::seed_from_u64(seed),
+            cosmetic: StdRng::seed_from_u64(seed ^ COSMETIC_STREAM),
+        }
+    }
+
+    pub fn gameplay(&mut self) -> &mut StdRng {
+        &mut self.gameplay
+    }
+
+    pub fn cosmetic(&mut self) -> &mut StdRng {
+        &mut self.cosmetic
+    }
+
+    /// A gameplay stream tied to a position, for things spawned in asset load order (which
+    /// isn't deterministic) like shelf stock.
+    pub fn at_position(&self, position: Vec3) -> StdRng {
+        let position_bits = (position.x.to_bits() as u64)
+            ^ ((position.y.to_bits() as u64) << 21)
+            ^ ((position.z.to_bits() as u64) << 42);
+        StdRng::seed_from_u64(self.seed ^ position_bits)
+    }
+}
+
+fn reseed_game_rng(seed: Res<GameSeed>, mut rng: ResMut<GameRng>) {
+    info!("seeding game rng with {}", seed.0);
+    *rng = GameRng::from_seed(seed.0);
+}
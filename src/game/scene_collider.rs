@@ -0,0 +1,228 @@
+use bevy::app::App;
+use bevy::asset::{AssetId, AssetServer, Assets, RecursiveDependencyLoadState};
+use bevy::core::Name;
+use bevy::hierarchy::{Children, HierarchyQueryExt, Parent};
+use bevy::math::{IVec3, Vec3};
+use bevy::prelude::{
+    debug, Commands, Component, Entity, Has, Local, Mesh, Mesh3d, OnAdd, Plugin, Quat, Query, Res,
+    SceneRoot, Trigger, Update, Visibility, With,
+};
+use bevy::render::mesh::MeshAabb;
+use bevy::scene::SceneInstanceReady;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::{Collider, ComputedColliderShape, RigidBody, RigidBodyDisabled};
+
+pub struct SceneColliderPlugin;
+impl Plugin for SceneColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(disable_body_until_scene_ready);
+        app.add_observer(generate_scene_colliders);
+        app.add_systems(Update, enable_body_without_scene);
+    }
+}
+
+/// Prefix of glTF nodes that are only there to be collided with
+const COLLISION_NODE_PREFIX: &str = "COL_";
+
+/// Colliders for a glTF scene, generated once it has spawned. Meshes under `COL_*` nodes become
+/// convex hulls and are hidden. A scene without any gets `fallback` for every mesh instead.
+#[derive(Component, Clone, Copy)]
+pub struct SceneColliders {
+    pub fallback: SceneColliderShape,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SceneColliderShape {
+    Aabb,
+    ConvexHull,
+    /// A box around each separate piece of the mesh, keeping the gaps between them, like the
+    /// boards and uprights of a shelf modelled as one mesh
+    PartAabbs,
+}
+
+/// Rests a body on the floor when its scene failed to load and it has no other colliders
+const MISSING_SCENE_COLLIDER_RADIUS: f32 = 0.1;
+
+/// Added to every entity given a collider from its scene
+#[derive(Component)]
+pub struct SceneCollider;
+
+// Bodies would fall through the floor while their scene loads
+fn disable_body_until_scene_ready(
+    trigger: Trigger<OnAdd, SceneColliders>,
+    mut commands: Commands,
+    body_q: Query<Has<RigidBody>>,
+) {
+    if let Ok(true) = body_q.get(trigger.entity()) {
+        commands.entity(trigger.entity()).insert(RigidBodyDisabled);
+    }
+}
+
+fn generate_scene_colliders(
+    trigger: Trigger<SceneInstanceReady>,
+    mut commands: Commands,
+    scene_colliders_q: Query<&SceneColliders>,
+    children_q: Query<&Children>,
+    mesh_q: Query<(&Mesh3d, &Parent)>,
+    name_q: Query<&Name>,
+    meshes: Res<Assets<Mesh>>,
+    mut cache: Local<HashMap<(AssetId<Mesh>, SceneColliderShape), Option<Collider>>>,
+) {
+    let root = trigger.entity();
+    let Ok(scene_colliders) = scene_colliders_q.get(root) else {
+        return;
+    };
+    let is_collision_node = |entity: Entity| {
+        name_q
+            .get(entity)
+            .is_ok_and(|name| name.as_str().starts_with(COLLISION_NODE_PREFIX))
+    };
+    // glTF primitives are spawned as children of the node that names them
+    let scene_meshes: Vec<(Entity, &Mesh3d, bool)> = children_q
+        .iter_descendants(root)
+        .filter_map(|entity| {
+            let (mesh, parent) = mesh_q.get(entity).ok()?;
+            Some((
+                entity,
+                mesh,
+                is_collision_node(entity) || is_collision_node(**parent),
+            ))
+        })
+        .collect();
+    let has_collision_nodes = scene_meshes.iter().any(|(_, _, collision)| *collision);
+
+    for (entity, mesh, collision) in scene_meshes {
+        let shape = if has_collision_nodes {
+            if !collision {
+                continue;
+            }
+            commands.entity(entity).insert(Visibility::Hidden);
+            SceneColliderShape::ConvexHull
+        } else {
+            scene_colliders.fallback
+        };
+        let collider = cache
+            .entry((mesh.id(), shape))
+            .or_insert_with(|| meshes.get(mesh).and_then(|mesh| mesh_collider(mesh, shape)));
+        if let Some(collider) = collider {
+            commands
+                .entity(entity)
+                .insert((collider.clone(), SceneCollider));
+        }
+    }
+    commands.entity(root).remove::<RigidBodyDisabled>();
+}
+
+// A scene that fails to load is never ready, so the body would stay disabled
+fn enable_body_without_scene(
+    mut commands: Commands,
+    body_q: Query<(Entity, &SceneRoot), (With<SceneColliders>, With<RigidBodyDisabled>)>,
+    children_q: Query<&Children>,
+    collider_q: Query<(), With<Collider>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, scene_root) in body_q.iter() {
+        let Some(RecursiveDependencyLoadState::Failed(error)) =
+            asset_server.get_recursive_dependency_load_state(scene_root.0.id())
+        else {
+            continue;
+        };
+        debug!("scene for {entity:?} failed to load, so it has no scene colliders: {error}");
+        let has_colliders = collider_q.contains(entity)
+            || children_q
+                .iter_descendants(entity)
+                .any(|child| collider_q.contains(child));
+        let mut entity_ec = commands.entity(entity);
+        if !has_colliders {
+            entity_ec.insert((
+                Collider::compound(vec![(
+                    Vec3::Y * MISSING_SCENE_COLLIDER_RADIUS,
+                    Quat::IDENTITY,
+                    Collider::ball(MISSING_SCENE_COLLIDER_RADIUS),
+                )]),
+                SceneCollider,
+            ));
+        }
+        entity_ec.remove::<RigidBodyDisabled>();
+    }
+}
+
+fn mesh_collider(mesh: &Mesh, shape: SceneColliderShape) -> Option<Collider> {
+    match shape {
+        SceneColliderShape::Aabb => {
+            let aabb = mesh.compute_aabb()?;
+            Some(Collider::compound(vec![(
+                aabb.center.into(),
+                Quat::IDENTITY,
+                Collider::cuboid(
+                    aabb.half_extents.x,
+                    aabb.half_extents.y,
+                    aabb.half_extents.z,
+                ),
+            )]))
+        }
+        SceneColliderShape::ConvexHull => {
+            Collider::from_bevy_mesh(mesh, &ComputedColliderShape::ConvexHull)
+        }
+        SceneColliderShape::PartAabbs => {
+            let parts = mesh_part_aabbs(mesh)?;
+            Some(Collider::compound(
+                parts
+                    .into_iter()
+                    .map(|(min, max)| {
+                        let half_extents = (max - min) / 2.0;
+                        (
+                            (min + max) / 2.0,
+                            Quat::IDENTITY,
+                            Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+                        )
+                    })
+                    .collect(),
+            ))
+        }
+    }
+}
+
+/// Bounds of each connected piece of a triangle mesh. Vertices are joined by position, since
+/// the faces of one piece don't share vertices where their normals differ.
+fn mesh_part_aabbs(mesh: &Mesh) -> Option<Vec<(Vec3, Vec3)>> {
+    let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+    let indices: Vec<usize> = mesh.indices()?.iter().collect();
+    // Vertices that round to the same tenth of a millimetre are one point
+    let mut points: HashMap<IVec3, usize> = HashMap::default();
+    let point_of_vertex: Vec<usize> = positions
+        .iter()
+        .map(|position| {
+            let key = (Vec3::from(*position) * 10_000.0).round().as_ivec3();
+            let next = points.len();
+            *points.entry(key).or_insert(next)
+        })
+        .collect();
+
+    // Union-find over the points, merged along every triangle
+    let mut parents: Vec<usize> = (0..points.len()).collect();
+    fn find(parents: &mut [usize], mut point: usize) -> usize {
+        while parents[point] != point {
+            parents[point] = parents[parents[point]];
+            point = parents[point];
+        }
+        point
+    }
+    for triangle in indices.chunks_exact(3) {
+        let root = find(&mut parents, point_of_vertex[triangle[0]]);
+        for &vertex in &triangle[1..] {
+            let other = find(&mut parents, point_of_vertex[vertex]);
+            parents[other] = root;
+        }
+    }
+
+    let mut bounds: Vec<Option<(Vec3, Vec3)>> = vec![None; points.len()];
+    for (vertex, position) in positions.iter().enumerate() {
+        let position = Vec3::from(*position);
+        let part = find(&mut parents, point_of_vertex[vertex]);
+        let (min, max) = bounds[part].get_or_insert((position, position));
+        *min = min.min(position);
+        *max = max.max(position);
+    }
+    Some(bounds.into_iter().flatten().collect())
+}
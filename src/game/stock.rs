@@ -0,0 +1,303 @@
+use crate::game::cart::ItemInCart;
+use crate::game::checkout::RoundFinished;
+use crate::game::item::{ItemPickup, ItemPickupCountry};
+use crate::game::map::knock_over::KnockedOver;
+use crate::game::map::{Category, CategoryDistribution, ItemCategory};
+use crate::game::rng::GameRng;
+use crate::game::scene_collider::{SceneColliderShape, SceneColliders};
+use crate::state::{AppState, InGameState};
+use bevy::app::App;
+use bevy::asset::AssetServer;
+use bevy::core::Name;
+use bevy::math::Vec3;
+use bevy::prelude::{
+    in_state, Commands, Component, Entity, Event, EventReader, EventWriter, FixedUpdate, Has,
+    IntoSystemConfigs, OnEnter, Plugin, Query, Reflect, ReflectResource, Res, ResMut, Resource,
+    SceneRoot, Time, Transform, With, Without,
+};
+use bevy::utils::HashMap;
+use bevy_egui::EguiPlugin;
+use bevy_inspector_egui::prelude::ReflectInspectorOptions;
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+use bevy_inspector_egui::InspectorOptions;
+use rand::distr::weighted::WeightedIndex;
+use rand::prelude::{Distribution, IndexedRandom};
+use rand::Rng;
+
+pub struct StockPlugin;
+impl Plugin for StockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), reset_restock_timer);
+        app.add_systems(
+            FixedUpdate,
+            (
+                release_taken_stock,
+                restock_after_checkout,
+                restock_shelves,
+                update_stock_stats,
+            )
+                .chain()
+                .run_if(in_state(InGameState::Playing)),
+        );
+        app.add_event::<RestockWave>();
+        app.insert_resource(StockResource {
+            restock_secs: 3.0,
+            taken_distance: 0.5,
+        });
+        app.init_resource::<StockBudget>();
+        app.init_resource::<StockStats>();
+        app.init_resource::<RestockTimer>();
+        app.register_type::<StockResource>();
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(ResourceInspectorPlugin::<StockResource>::default());
+        }
+    }
+}
+
+#[derive(Resource, InspectorOptions, Reflect)]
+#[reflect(Resource, InspectorOptions)]
+pub struct StockResource {
+    /// How often one emptied slot somewhere in the store is restocked
+    restock_secs: f32,
+    /// How far an item can be nudged before its slot counts as empty
+    taken_distance: f32,
+}
+
+/// Most items in the store at once, since every item is a dynamic body. Restocking waits
+/// until there's room.
+#[derive(Resource)]
+pub struct StockBudget {
+    pub max_items: usize,
+}
+impl Default for StockBudget {
+    fn default() -> Self {
+        StockBudget {
+            max_items: if cfg!(target_arch = "wasm32") {
+                300
+            } else {
+                800
+            },
+        }
+    }
+}
+
+/// Seconds since a slot was last restocked on its own
+#[derive(Resource, Default)]
+struct RestockTimer(f32);
+
+/// Fills every emptied slot at once, budget allowing. Sent after each checkout.
+#[derive(Event)]
+pub struct RestockWave;
+
+/// Items still out in the store, so not counting anything already in the basket
+#[derive(Resource, Default)]
+pub struct StockStats {
+    pub total: usize,
+    pub by_category: HashMap<Category, usize>,
+    pub by_country: HashMap<ItemPickupCountry, usize>,
+}
+
+/// Where a shop object holds its stock, relative to itself
+#[derive(Component, Default)]
+pub struct StockSlots(pub Vec<StockSlot>);
+impl StockSlots {
+    pub fn new(slots: impl IntoIterator<Item = Vec3>) -> Self {
+        StockSlots(
+            slots
+                .into_iter()
+                .map(|local| StockSlot {
+                    local,
+                    state: SlotState::Unstocked,
+                })
+                .collect(),
+        )
+    }
+}
+
+pub struct StockSlot {
+    pub local: Vec3,
+    pub state: SlotState,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SlotState {
+    /// Waiting for its first item
+    Unstocked,
+    Stocked(Entity),
+    /// Its item was taken, restocked over time or in a wave
+    Empty,
+}
+
+fn release_taken_stock(
+    mut shelf_q: Query<(&Transform, &mut StockSlots)>,
+    item_q: Query<(&Transform, Has<ItemInCart>), With<ItemPickup>>,
+    settings: Res<StockResource>,
+) {
+    for (shelf_t, mut slots) in shelf_q.iter_mut() {
+        for slot in slots.0.iter_mut() {
+            let SlotState::Stocked(item) = slot.state else {
+                continue;
+            };
+            let taken = match item_q.get(item) {
+                Ok((item_t, in_cart)) => {
+                    in_cart
+                        || item_t
+                            .translation
+                            .distance(shelf_t.transform_point(slot.local))
+                            > settings.taken_distance
+                }
+                // Checked out or otherwise gone
+                Err(_) => true,
+            };
+            if taken {
+                slot.state = SlotState::Empty;
+            }
+        }
+    }
+}
+
+fn restock_after_checkout(
+    mut finished_events: EventReader<RoundFinished>,
+    mut wave_events: EventWriter<RestockWave>,
+) {
+    if finished_events.read().count() > 0 {
+        wave_events.send(RestockWave);
+    }
+}
+
+fn reset_restock_timer(mut restock_timer: ResMut<RestockTimer>) {
+    restock_timer.0 = 0.0;
+}
+
+fn restock_shelves(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut shelf_q: Query<
+        (Entity, &Transform, &CategoryDistribution, &mut StockSlots),
+        Without<KnockedOver>,
+    >,
+    item_q: Query<(), With<ItemPickup>>,
+    budget: Res<StockBudget>,
+    settings: Res<StockResource>,
+    mut wave_events: EventReader<RestockWave>,
+    mut restock_timer: ResMut<RestockTimer>,
+    mut game_rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let mut room = budget.max_items.saturating_sub(item_q.iter().count());
+    let wave = wave_events.read().count() > 0;
+    restock_timer.0 += time.delta_secs();
+    let trickle = restock_timer.0 >= settings.restock_secs;
+    if trickle {
+        restock_timer.0 = 0.0;
+    }
+
+    // Query order isn't stable, so shelves are filled in order of where they stand and each
+    // item is seeded by where it sits. Otherwise the budget and the rng would pick differently
+    // every run.
+    let mut shelves: Vec<_> = shelf_q
+        .iter_mut()
+        .filter(|(_, _, category_dist, _)| !category_dist.0.is_empty())
+        .collect();
+    shelves.sort_by(|(_, a, _, _), (_, b, _, _)| {
+        let (a, b) = (a.translation, b.translation);
+        a.x.total_cmp(&b.x)
+            .then(a.z.total_cmp(&b.z))
+            .then(a.y.total_cmp(&b.y))
+    });
+    let mut empty_slots = vec![];
+    for (shelf, shelf_t, category_dist, mut slots) in shelves {
+        for (index, slot) in slots.0.iter_mut().enumerate() {
+            let restock = match slot.state {
+                SlotState::Unstocked => true,
+                SlotState::Empty if wave => true,
+                SlotState::Empty => {
+                    empty_slots.push((shelf, index));
+                    false
+                }
+                SlotState::Stocked(_) => false,
+            };
+            if !restock || room == 0 {
+                continue;
+            }
+            let position = shelf_t.transform_point(slot.local);
+            let item = spawn_stock_item(
+                &mut commands,
+                &asset_server,
+                category_dist,
+                position,
+                &mut game_rng.at_position(position),
+            );
+            slot.state = SlotState::Stocked(item);
+            room -= 1;
+        }
+    }
+
+    if !trickle || room == 0 {
+        return;
+    }
+    let Some(&(shelf, index)) = empty_slots.choose(game_rng.gameplay()) else {
+        return;
+    };
+    let Ok((_, shelf_t, category_dist, mut slots)) = shelf_q.get_mut(shelf) else {
+        return;
+    };
+    let position = shelf_t.transform_point(slots.0[index].local);
+    let item = spawn_stock_item(
+        &mut commands,
+        &asset_server,
+        category_dist,
+        position,
+        &mut game_rng.at_position(position),
+    );
+    slots.0[index].state = SlotState::Stocked(item);
+}
+
+fn spawn_stock_item(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    category_dist: &CategoryDistribution,
+    position: Vec3,
+    rng: &mut impl Rng,
+) -> Entity {
+    let category_weights: Vec<f32> = category_dist.0.iter().map(|(weight, _)| *weight).collect();
+    let dist = WeightedIndex::new(&category_weights).unwrap();
+    let category = category_dist.0[dist.sample(rng)].1;
+    commands
+        .spawn((
+            Name::new(format!("{category:?} Item")),
+            SceneRoot(asset_server.load(category.item_path())),
+            Transform::from_translation(position),
+            ItemPickup,
+            ItemCategory(category),
+            SceneColliders {
+                fallback: SceneColliderShape::ConvexHull,
+            },
+            rng.random::<ItemPickupCountry>(),
+        ))
+        .id()
+}
+
+fn update_stock_stats(
+    item_q: Query<
+        (Option<&ItemCategory>, &ItemPickupCountry),
+        (With<ItemPickup>, Without<ItemInCart>),
+    >,
+    mut stats: ResMut<StockStats>,
+) {
+    let mut by_category = HashMap::new();
+    let mut by_country = HashMap::new();
+    let mut total = 0;
+    for (category, country) in item_q.iter() {
+        total += 1;
+        if let Some(category) = category {
+            *by_category.entry(category.0).or_default() += 1;
+        }
+        *by_country.entry(*country).or_default() += 1;
+    }
+    *stats = StockStats {
+        total,
+        by_category,
+        by_country,
+    };
+}
@@ -1,19 +1,28 @@
 mod camera;
 mod game;
 mod hierarchy;
+mod quality;
+mod retro_arcade;
 mod state;
 mod ui;
 
 use crate::camera::CameraPlugin;
 use crate::game::game::GamePlugin;
+use crate::game::headless::headless_app;
+use crate::game::recording::InputMode;
+use crate::quality::QualityPlugin;
+use crate::retro_arcade::RetroArcadePlugin;
 use crate::state::StatePlugin;
+use crate::ui::loading::UILoadingPlugin;
 use crate::ui::title::home::UITitleMenuHomePlugin;
+use crate::ui::title::settings::UITitleMenuSettingsPlugin;
 use bevy::asset::AssetMetaCheck;
+use bevy::log::LogPlugin;
 use bevy::prelude::{
     default, App, AssetPlugin, ImagePlugin, MeshPickingPlugin, MeshPickingSettings, PluginGroup,
     RayCastVisibility, Window, WindowPlugin,
 };
-use bevy::render::settings::{Backends, RenderCreation, WgpuSettings};
+use bevy::render::settings::{Backends, RenderCreation, WgpuSettings, WgpuSettingsPriority};
 use bevy::render::RenderPlugin;
 use bevy::DefaultPlugins;
 use bevy_egui::EguiPlugin;
@@ -26,6 +35,14 @@ use bevy_rapier3d::prelude::{NoUserData, RapierDebugRenderPlugin, RapierPhysicsP
 // static ALLOCATOR: talc::TalckWasm = unsafe { talc::TalckWasm::new_global() };
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--headless") {
+        // e.g. `--headless --playback bug.txt` to reproduce a run without a window
+        let mut app = headless_app();
+        app.add_plugins(LogPlugin::default());
+        app.insert_resource(InputMode::from_args());
+        app.run();
+        return;
+    }
     let mut app = App::new();
     app.add_plugins(
         DefaultPlugins
@@ -43,17 +60,35 @@ fn main() {
                 ..default()
             })
             .set(RenderPlugin {
-                render_creation: RenderCreation::Automatic(WgpuSettings { ..default() }),
+                render_creation: RenderCreation::Automatic(WgpuSettings {
+                    // Keep to WebGL2's limits even on desktop, to try the fallback build natively
+                    priority: if cfg!(feature = "webgl2") {
+                        WgpuSettingsPriority::WebGL2
+                    } else {
+                        WgpuSettingsPriority::Functionality
+                    },
+                    ..default()
+                }),
                 ..default()
             }),
     )
     .add_plugins(MeshPickingPlugin)
-    .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
     .add_plugins(EguiPlugin)
     .add_plugins(UITitleMenuHomePlugin)
+    .add_plugins(UITitleMenuSettingsPlugin)
+    .add_plugins(UILoadingPlugin)
     .add_plugins(StatePlugin)
     .add_plugins(CameraPlugin)
+    .add_plugins(RetroArcadePlugin)
+    .add_plugins(QualityPlugin)
     .add_plugins(GamePlugin);
+    let input_mode = InputMode::from_args();
+    if input_mode.is_deterministic() {
+        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default().in_fixed_schedule());
+    } else {
+        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default());
+    }
+    app.insert_resource(input_mode);
     if cfg!(debug_assertions) {
         app.add_plugins(RapierDebugRenderPlugin::default())
             .add_plugins(WorldInspectorPlugin::new());
@@ -0,0 +1,163 @@
+use crate::camera::{GameCamera, UICamera};
+use crate::game::effects::particles::ParticleBudget;
+use crate::game::map::lighting::{LightBudget, StoreLight};
+use bevy::app::App;
+use bevy::core_pipeline::bloom::Bloom;
+use bevy::pbr::CascadeShadowConfig;
+use bevy::prelude::{
+    resource_changed, Camera, Commands, DirectionalLight, Entity, Has, IntoSystemConfigs, Msaa, Or,
+    Plugin, Query, Reflect, ReflectResource, Res, ResMut, Resource, Update, With,
+};
+
+pub struct QualityPlugin;
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            apply_quality_preset.run_if(resource_changed::<QualityPreset>),
+        );
+        app.init_resource::<QualityPreset>();
+        app.register_type::<QualityPreset>();
+    }
+}
+
+/// Trades looks for frame rate. Point light count and point light shadows take effect the next
+/// time a store is lit, everything else right away.
+#[derive(Resource, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    High,
+}
+impl Default for QualityPreset {
+    fn default() -> Self {
+        if cfg!(feature = "webgl2") {
+            QualityPreset::Low
+        } else if cfg!(target_arch = "wasm32") {
+            QualityPreset::Medium
+        } else {
+            QualityPreset::High
+        }
+    }
+}
+impl QualityPreset {
+    pub const ALL: [QualityPreset; 3] = [
+        QualityPreset::Low,
+        QualityPreset::Medium,
+        QualityPreset::High,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QualityPreset::Low => "Low",
+            QualityPreset::Medium => "Medium",
+            QualityPreset::High => "High",
+        }
+    }
+
+    pub fn light_budget(&self) -> LightBudget {
+        match self {
+            QualityPreset::Low => LightBudget {
+                max_point_lights: 12,
+                max_shadow_casters: 0,
+                directional_shadows: false,
+                shadow_cascades: 1,
+                shadow_distance: 30.0,
+            },
+            QualityPreset::Medium => LightBudget {
+                max_point_lights: 24,
+                max_shadow_casters: 0,
+                directional_shadows: false,
+                shadow_cascades: 1,
+                shadow_distance: 30.0,
+            },
+            QualityPreset::High => LightBudget {
+                max_point_lights: 64,
+                max_shadow_casters: 4,
+                directional_shadows: true,
+                // WebGL2 only has room for one cascade per light
+                shadow_cascades: if cfg!(feature = "webgl2") { 1 } else { 4 },
+                shadow_distance: 80.0,
+            },
+        }
+    }
+
+    pub fn particle_budget(&self) -> ParticleBudget {
+        ParticleBudget {
+            max_particles: match self {
+                QualityPreset::Low => 100,
+                QualityPreset::Medium => 200,
+                QualityPreset::High => 400,
+            },
+        }
+    }
+
+    /// Bloom needs an HDR camera, so without it the camera renders straight to SDR
+    fn bloom(&self) -> Option<Bloom> {
+        match self {
+            QualityPreset::Low => None,
+            QualityPreset::Medium | QualityPreset::High => Some(Bloom::NATURAL),
+        }
+    }
+
+    fn msaa(&self) -> Msaa {
+        match self {
+            QualityPreset::Low => Msaa::Off,
+            // 4x is the only multisampling WebGL2 guarantees
+            QualityPreset::Medium | QualityPreset::High => Msaa::Sample4,
+        }
+    }
+}
+
+fn apply_quality_preset(
+    mut commands: Commands,
+    preset: Res<QualityPreset>,
+    mut light_budget: ResMut<LightBudget>,
+    mut particle_budget: ResMut<ParticleBudget>,
+    mut camera_q: Query<
+        (Entity, &mut Camera, Has<GameCamera>),
+        Or<(With<GameCamera>, With<UICamera>)>,
+    >,
+    mut sun_q: Query<(&mut DirectionalLight, &mut CascadeShadowConfig), With<StoreLight>>,
+) {
+    *light_budget = preset.light_budget();
+    *particle_budget = preset.particle_budget();
+    let bloom = preset.bloom();
+    // The UI camera draws over the game camera's image, so has to share its HDR and MSAA
+    for (camera_entity, mut camera, is_game_camera) in camera_q.iter_mut() {
+        let mut camera_ec = commands.entity(camera_entity);
+        camera.hdr = bloom.is_some();
+        camera_ec.insert(preset.msaa());
+        if !is_game_camera {
+            continue;
+        }
+        match &bloom {
+            Some(bloom) => camera_ec.insert(bloom.clone()),
+            None => camera_ec.remove::<Bloom>(),
+        };
+    }
+    for (mut sun, mut cascades) in sun_q.iter_mut() {
+        sun.shadows_enabled = light_budget.directional_shadows;
+        *cascades = light_budget.cascade_shadow_config();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_quality_presets_never_cut_budgets() {
+        for pair in QualityPreset::ALL.windows(2) {
+            let (lower, higher) = (pair[0], pair[1]);
+            let (lower_lights, higher_lights) = (lower.light_budget(), higher.light_budget());
+            assert!(lower_lights.max_point_lights <= higher_lights.max_point_lights);
+            assert!(lower_lights.max_shadow_casters <= higher_lights.max_shadow_casters);
+            assert!(lower_lights.shadow_cascades <= higher_lights.shadow_cascades);
+            assert!(
+                lower.particle_budget().max_particles <= higher.particle_budget().max_particles
+            );
+        }
+    }
+}
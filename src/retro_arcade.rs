@@ -0,0 +1,255 @@
+use crate::camera::GameCamera;
+use bevy::app::App;
+use bevy::color::{Color, LinearRgba};
+use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::image::BevyDefault;
+use bevy::math::{Vec2, Vec3};
+use bevy::prelude::{
+    Camera, Commands, Component, DirectAssetAccessExt, Entity, FromWorld, Plugin, Query, Reflect,
+    ReflectResource, Res, Resource, Update, With, World,
+};
+use bevy::render::extract_component::{
+    ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
+    UniformComponentPlugin,
+};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
+use bevy::render::render_resource::{
+    BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+    ColorTargetState, ColorWrites, FragmentState, MultisampleState, Operations, PipelineCache,
+    PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages, ShaderType, TextureFormat,
+    TextureSampleType,
+};
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::view::ViewTarget;
+use bevy::render::RenderApp;
+use bevy_egui::EguiPlugin;
+use bevy_inspector_egui::prelude::ReflectInspectorOptions;
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+use bevy_inspector_egui::InspectorOptions;
+
+const SHADER_ASSET_PATH: &str = "shaders/retro_arcade.wgsl";
+
+/// Pixelates and tints the game camera's image, drawn after tonemapping
+pub struct RetroArcadePlugin;
+impl Plugin for RetroArcadePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ExtractComponentPlugin::<RetroArcade>::default(),
+            UniformComponentPlugin::<RetroArcade>::default(),
+        ));
+        app.add_systems(Update, apply_retro_arcade_settings);
+        app.insert_resource(RetroArcadeSettings {
+            enabled: false,
+            pixel_size: 5.0,
+            tint: Color::linear_rgb(1.2, 1.1, 0.8),
+        });
+        app.register_type::<RetroArcadeSettings>();
+        if app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(ResourceInspectorPlugin::<RetroArcadeSettings>::default());
+        }
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<RetroArcadeNode>>(Core3d, RetroArcadeLabel)
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::Tonemapping,
+                    RetroArcadeLabel,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<RetroArcadePipeline>();
+    }
+}
+
+/// The "retro mode" option in settings
+#[derive(Resource, InspectorOptions, Reflect)]
+#[reflect(Resource, InspectorOptions)]
+pub struct RetroArcadeSettings {
+    pub enabled: bool,
+    /// Size of each block, in screen pixels
+    #[inspector(min = 1.0, max = 32.0)]
+    pub pixel_size: f32,
+    pub tint: Color,
+}
+
+/// Uniforms for `retro_arcade.wgsl`, kept on the game camera while retro mode is on
+#[derive(Component, Clone, Copy, PartialEq, ExtractComponent, ShaderType)]
+pub struct RetroArcade {
+    /// Size of the camera's image in physical pixels
+    resolution: Vec2,
+    pixel_size: f32,
+    tint: Vec3,
+}
+
+// Only touches the camera when something changed, so the uniform isn't reinserted every frame
+fn apply_retro_arcade_settings(
+    mut commands: Commands,
+    settings: Res<RetroArcadeSettings>,
+    camera_q: Query<(Entity, &Camera, Option<&RetroArcade>), With<GameCamera>>,
+) {
+    for (camera_entity, camera, current) in camera_q.iter() {
+        let resolution = camera
+            .physical_viewport_size()
+            .unwrap_or_default()
+            .as_vec2();
+        if !settings.enabled || resolution == Vec2::ZERO {
+            if current.is_some() {
+                commands.entity(camera_entity).remove::<RetroArcade>();
+            }
+            continue;
+        }
+        let tint = LinearRgba::from(settings.tint);
+        let retro_arcade = RetroArcade {
+            resolution,
+            pixel_size: settings.pixel_size.max(1.0),
+            tint: Vec3::new(tint.red, tint.green, tint.blue),
+        };
+        if current != Some(&retro_arcade) {
+            commands.entity(camera_entity).insert(retro_arcade);
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct RetroArcadeLabel;
+
+#[derive(Default)]
+struct RetroArcadeNode;
+
+impl ViewNode for RetroArcadeNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static DynamicUniformIndex<RetroArcade>,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, settings_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let retro_pipeline = world.resource::<RetroArcadePipeline>();
+        let pipeline_id = if view_target.is_hdr() {
+            retro_pipeline.hdr_pipeline_id
+        } else {
+            retro_pipeline.pipeline_id
+        };
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline_id)
+        else {
+            // Still compiling
+            return Ok(());
+        };
+        let Some(settings_binding) = world
+            .resource::<ComponentUniforms<RetroArcade>>()
+            .uniforms()
+            .binding()
+        else {
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "retro_arcade_bind_group",
+            &retro_pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &retro_pipeline.sampler,
+                settings_binding.clone(),
+            )),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("retro_arcade_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[settings_index.index()]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct RetroArcadePipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline_id: CachedRenderPipelineId,
+    /// HDR cameras post process in a float texture, so need their own pipeline
+    hdr_pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for RetroArcadePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "retro_arcade_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<RetroArcade>(true),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+        let shader = world.load_asset(SHADER_ASSET_PATH);
+        let mut queue_pipeline = |format: TextureFormat| {
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("retro_arcade_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader: shader.clone(),
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format,
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    push_constant_ranges: vec![],
+                    zero_initialize_workgroup_memory: false,
+                })
+        };
+        let pipeline_id = queue_pipeline(TextureFormat::bevy_default());
+        let hdr_pipeline_id = queue_pipeline(ViewTarget::TEXTURE_FORMAT_HDR);
+        RetroArcadePipeline {
+            layout,
+            sampler,
+            pipeline_id,
+            hdr_pipeline_id,
+        }
+    }
+}
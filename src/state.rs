@@ -5,6 +5,8 @@ use bevy::prelude::{AppExtStates, NextState, OnEnter, OnExit, Plugin, ResMut, St
 pub enum AppState {
     #[default]
     TitleMenu,
+    /// Generating the store and loading everything in it
+    Loading,
     InGame,
 }
 
@@ -22,6 +24,7 @@ pub enum InGameState {
     #[default]
     None,
     Playing,
+    Replay,
 }
 
 pub struct StatePlugin;
@@ -0,0 +1,46 @@
+use crate::game::loading::LevelAssets;
+use crate::state::AppState;
+use bevy::app::App;
+use bevy::asset::AssetServer;
+use bevy::prelude::{in_state, IntoSystemConfigs, Plugin, Res, Update};
+use bevy_egui::egui::{Align2, Color32, Frame, ProgressBar, RichText, TextStyle};
+use bevy_egui::{egui, EguiContexts};
+
+const PROGRESS_BAR_WIDTH: f32 = 400.0;
+
+pub struct UILoadingPlugin;
+impl Plugin for UILoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            loading_screen_system.run_if(in_state(AppState::Loading)),
+        );
+    }
+}
+
+fn loading_screen_system(
+    mut contexts: EguiContexts,
+    level_assets: Option<Res<LevelAssets>>,
+    asset_server: Res<AssetServer>,
+) {
+    let (settled, total) =
+        level_assets.map_or((0, 0), |level_assets| level_assets.progress(&asset_server));
+    let ctx = contexts.ctx_mut();
+    egui::CentralPanel::default()
+        .frame(Frame::default().fill(Color32::BLACK))
+        .show(ctx, |_| {});
+    egui::Area::new("loading_area".into())
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(
+                RichText::new("Stocking the shelves...")
+                    .text_style(TextStyle::Heading)
+                    .size(32.),
+            );
+            ui.add(
+                ProgressBar::new(settled as f32 / total.max(1) as f32)
+                    .desired_width(PROGRESS_BAR_WIDTH)
+                    .text(format!("{settled} / {total}")),
+            );
+        });
+}
@@ -1 +1,2 @@
-pub mod title;
\ No newline at end of file
+pub mod loading;
+pub mod title;
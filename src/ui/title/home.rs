@@ -1,9 +1,11 @@
+use crate::game::game::GameMode;
+use crate::game::recording::InputMode;
 use crate::state::{AppState, TitleMenuState};
 use bevy::app::App;
 use bevy::prelude::{
     default, in_state, AssetServer, Commands, Component, DespawnRecursiveExt, Display, Entity,
     ImageNode, IntoSystemConfigs, NextState, Node, OnEnter, OnExit, Plugin, PositionType, Query,
-    Res, ResMut, Update, Val, With,
+    Res, ResMut, Startup, Update, Val, With,
 };
 use bevy_egui::egui::{Button, Color32, Frame, Response, RichText, TextStyle, Ui, Vec2};
 use bevy_egui::{egui, EguiContexts};
@@ -11,6 +13,7 @@ use bevy_egui::{egui, EguiContexts};
 pub struct UITitleMenuHomePlugin;
 impl Plugin for UITitleMenuHomePlugin {
     fn build(&self, app: &mut App) {
+        app.add_systems(Startup, skip_title_for_playback);
         app.add_systems(OnEnter(TitleMenuState::Home), title_menu_setup);
         app.add_systems(OnExit(TitleMenuState::Home), title_menu_cleanup);
         app.add_systems(
@@ -20,13 +23,13 @@ impl Plugin for UITitleMenuHomePlugin {
     }
 }
 
-const PANEL_WIDTH: f32 = 300.0;
+pub(super) const PANEL_WIDTH: f32 = 300.0;
 const PANEL_BUTTON_SIZE: Vec2 = Vec2::new(286.0, 40.0);
 
 #[derive(Component)]
 struct TitleMenuTag;
 
-fn title_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub(super) fn title_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     let title_back = asset_server.load("images/title_back.png");
     commands.spawn((
         ImageNode::new(title_back),
@@ -43,10 +46,18 @@ fn title_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
+// A recording is played back from the start of a level, without anyone at the menu
+fn skip_title_for_playback(input_mode: Res<InputMode>, mut app_state: ResMut<NextState<AppState>>) {
+    if let InputMode::Playback(_) = *input_mode {
+        app_state.set(AppState::Loading);
+    }
+}
+
 fn title_menu_system(
     mut contexts: EguiContexts,
     mut title_menu_state: ResMut<NextState<TitleMenuState>>,
     mut app_state: ResMut<NextState<AppState>>,
+    mut game_mode: ResMut<GameMode>,
 ) {
     egui::SidePanel::left("title_left_panel")
         .frame(
@@ -63,27 +74,47 @@ fn title_menu_system(
                     .text_style(TextStyle::Heading)
                     .size(32.),
             );
-            new_game_button(ui, &mut app_state);
+            new_game_button(ui, &mut app_state, &mut game_mode);
+            rampage_button(ui, &mut app_state, &mut game_mode);
             settings_button(ui, &mut title_menu_state);
         });
 }
 
-fn title_menu_cleanup(cleanup: Query<Entity, With<TitleMenuTag>>, mut commands: Commands) {
+pub(super) fn title_menu_cleanup(
+    cleanup: Query<Entity, With<TitleMenuTag>>,
+    mut commands: Commands,
+) {
     for entity in &cleanup {
         commands.entity(entity).despawn_recursive();
     }
 }
 
-fn title_button(ui: &mut Ui, text: &str) -> Response {
+pub(super) fn title_button(ui: &mut Ui, text: &str) -> Response {
     ui.add_sized(
         PANEL_BUTTON_SIZE,
         Button::new(RichText::new(text).size(22.)),
     )
 }
 
-fn new_game_button(ui: &mut Ui, app_state: &mut ResMut<NextState<AppState>>) {
+fn new_game_button(
+    ui: &mut Ui,
+    app_state: &mut ResMut<NextState<AppState>>,
+    game_mode: &mut ResMut<GameMode>,
+) {
     if title_button(ui, "New Game").clicked() {
-        app_state.set(AppState::InGame);
+        **game_mode = GameMode::Shopping;
+        app_state.set(AppState::Loading);
+    }
+}
+
+fn rampage_button(
+    ui: &mut Ui,
+    app_state: &mut ResMut<NextState<AppState>>,
+    game_mode: &mut ResMut<GameMode>,
+) {
+    if title_button(ui, "Rampage").clicked() {
+        **game_mode = GameMode::Rampage;
+        app_state.set(AppState::Loading);
     }
 }
 
@@ -1 +1,2 @@
-pub mod home;
\ No newline at end of file
+pub mod home;
+pub mod settings;
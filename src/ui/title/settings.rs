@@ -0,0 +1,94 @@
+use crate::quality::QualityPreset;
+use crate::retro_arcade::RetroArcadeSettings;
+use crate::state::TitleMenuState;
+use crate::ui::title::home::{title_button, title_menu_cleanup, title_menu_setup, PANEL_WIDTH};
+use bevy::app::App;
+use bevy::color::{Color, LinearRgba};
+use bevy::prelude::{
+    in_state, IntoSystemConfigs, NextState, OnEnter, OnExit, Plugin, ResMut, Update,
+};
+use bevy_egui::egui::{Color32, DragValue, Frame, RichText, Slider, TextStyle};
+use bevy_egui::{egui, EguiContexts};
+
+pub struct UITitleMenuSettingsPlugin;
+impl Plugin for UITitleMenuSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(TitleMenuState::Settings), title_menu_setup);
+        app.add_systems(OnExit(TitleMenuState::Settings), title_menu_cleanup);
+        app.add_systems(
+            Update,
+            settings_menu_system.run_if(in_state(TitleMenuState::Settings)),
+        );
+    }
+}
+
+fn settings_menu_system(
+    mut contexts: EguiContexts,
+    mut title_menu_state: ResMut<NextState<TitleMenuState>>,
+    mut retro_arcade: ResMut<RetroArcadeSettings>,
+    mut quality_preset: ResMut<QualityPreset>,
+) {
+    egui::SidePanel::left("title_left_panel")
+        .frame(
+            Frame::default()
+                .inner_margin(8.)
+                .fill(Color32::from_black_alpha(200)),
+        )
+        .resizable(false)
+        .show_separator_line(false)
+        .exact_width(PANEL_WIDTH)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(
+                RichText::new("Settings")
+                    .text_style(TextStyle::Heading)
+                    .size(32.),
+            );
+            ui.label(RichText::new("Quality").size(22.));
+            ui.horizontal(|ui| {
+                let mut selected = *quality_preset;
+                for preset in QualityPreset::ALL {
+                    ui.selectable_value(
+                        &mut selected,
+                        preset,
+                        RichText::new(preset.name()).size(18.),
+                    );
+                }
+                // Only touch the resource on a change, it's reapplied whenever it changes
+                if selected != *quality_preset {
+                    *quality_preset = selected;
+                }
+            });
+            ui.checkbox(
+                &mut retro_arcade.enabled,
+                RichText::new("Retro mode").size(22.),
+            );
+            ui.add_enabled(
+                retro_arcade.enabled,
+                Slider::new(&mut retro_arcade.pixel_size, 2.0..=12.0)
+                    .step_by(1.0)
+                    .text("Pixel size"),
+            );
+            ui.add_enabled_ui(retro_arcade.enabled, |ui| {
+                ui.horizontal(|ui| {
+                    // Tints above 1 brighten, so it's edited in linear space with room to spare
+                    let tint = LinearRgba::from(retro_arcade.tint);
+                    let mut rgb = [tint.red, tint.green, tint.blue];
+                    for (channel, name) in rgb.iter_mut().zip(["R", "G", "B"]) {
+                        ui.add(
+                            DragValue::new(channel)
+                                .speed(0.01)
+                                .range(0.0..=2.0)
+                                .prefix(name),
+                        );
+                    }
+                    ui.label("Tint");
+                    if rgb != [tint.red, tint.green, tint.blue] {
+                        retro_arcade.tint = Color::linear_rgb(rgb[0], rgb[1], rgb[2]);
+                    }
+                });
+            });
+            if title_button(ui, "Back").clicked() {
+                title_menu_state.set(TitleMenuState::Home);
+            }
+        });
+}